@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::MultiGzDecoder;
+use regex::Regex;
+
+/// A member written to disk by [`TarExtractor`].
+pub(crate) struct ExtractedMember {
+    /// Member path inside the archive.
+    pub(crate) member: String,
+    pub(crate) path: PathBuf,
+    /// GSM accession parsed from the member name (e.g. `GSM15089.CEL.gz`).
+    pub(crate) gsm: Option<String>,
+}
+
+/// Streaming extractor for tar archives such as `GSEnnn_RAW.tar`.
+///
+/// The archive is read sequentially, so only selected members are written and
+/// nothing else is buffered. Members are selected by `pattern` and by GSM
+/// accession; both must match when given.
+pub(crate) struct TarExtractor {
+    odir: PathBuf,
+    pattern: Option<Regex>,
+    gsm: Option<Vec<String>>,
+    gunzip: bool,
+}
+
+impl TarExtractor {
+    pub(crate) fn new(odir: &Path) -> Self {
+        Self {
+            odir: odir.to_path_buf(),
+            pattern: None,
+            gsm: None,
+            gunzip: false,
+        }
+    }
+
+    /// Only extract members whose name matches `pattern`.
+    pub(crate) fn pattern(&mut self, pattern: Regex) {
+        self.pattern = Some(pattern)
+    }
+
+    /// Only extract members belonging to one of the `gsm` accessions.
+    pub(crate) fn gsm(&mut self, gsm: Vec<String>) {
+        self.gsm = Some(gsm)
+    }
+
+    /// Whether to decompress `.gz` members while extracting, dropping the
+    /// `.gz` suffix from the written file (default: false).
+    pub(crate) fn gunzip(&mut self, gunzip: bool) {
+        self.gunzip = gunzip
+    }
+
+    /// Extract the selected members of `tarfile`, which may be gzip-compressed
+    /// (`.tar.gz` or `.tgz`).
+    pub(crate) fn extract(&self, tarfile: &Path) -> Result<Vec<ExtractedMember>> {
+        let file =
+            File::open(tarfile).with_context(|| format!("Failed to open {}", tarfile.display()))?;
+        let reader = BufReader::new(file);
+        let name = tarfile.to_string_lossy();
+        if name.ends_with(".gz") || name.ends_with(".tgz") {
+            self.extract_from(MultiGzDecoder::new(reader))
+        } else {
+            self.extract_from(reader)
+        }
+        .with_context(|| format!("Failed to extract {}", tarfile.display()))
+    }
+
+    fn extract_from<R: Read>(&self, reader: R) -> Result<Vec<ExtractedMember>> {
+        let mut archive = tar::Archive::new(reader);
+        let mut out = Vec::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let member = entry.path()?.to_string_lossy().into_owned();
+            // Members are written flat into `odir`, never outside of it.
+            let fname = Path::new(&member)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .ok_or_else(|| anyhow!("Invalid member name: {}", member))?;
+            let gsm = gsm_from_name(&fname);
+            if !self.is_selected(&fname, gsm) {
+                continue;
+            }
+            let gsm = gsm.map(|gsm| gsm.to_string());
+            let path = match fname.strip_suffix(".gz") {
+                Some(stem) if self.gunzip => {
+                    let path = self.odir.join(stem);
+                    write_member(&mut MultiGzDecoder::new(&mut entry), &path)
+                        .with_context(|| format!("Failed to decompress {}", member))?;
+                    path
+                }
+                _ => {
+                    let path = self.odir.join(&fname);
+                    write_member(&mut entry, &path)
+                        .with_context(|| format!("Failed to extract {}", member))?;
+                    path
+                }
+            };
+            out.push(ExtractedMember { member, path, gsm });
+        }
+        Ok(out)
+    }
+
+    fn is_selected(&self, fname: &str, gsm: Option<&str>) -> bool {
+        self.pattern.as_ref().map_or(true, |re| re.is_match(fname))
+            && self.gsm.as_ref().map_or(true, |accessions| {
+                gsm.is_some_and(|gsm| accessions.iter().any(|acc| acc == gsm))
+            })
+    }
+}
+
+fn write_member<R: Read>(reader: &mut R, path: &Path) -> io::Result<u64> {
+    let mut file = File::create(path)?;
+    io::copy(reader, &mut file)
+}
+
+/// Parse the GSM accession that GEO puts at the start of `RAW.tar` member
+/// names, e.g. `GSM15089` from `GSM15089.CEL.gz` or `GSM15089_sample.txt.gz`.
+pub(crate) fn gsm_from_name(name: &str) -> Option<&str> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^GSM\d+")
+            .with_context(|| "Failed to create regex")
+            .unwrap()
+    })
+    .find(name)
+    .map(|m| m.as_str())
+}
@@ -0,0 +1,377 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::downloader::Validators;
+
+/// Name of the index file in the cache root.
+const INDEX_FILE: &str = "index.json";
+
+/// Name of the file in the cache root locked while a session changes the
+/// cache.
+const LOCK_FILE: &str = ".lock";
+
+/// A file recorded in the cache index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CacheEntry {
+    /// URL the file was fetched from, the key of the index.
+    pub(crate) url: String,
+    /// File name the file was downloaded as.
+    pub(crate) fname: String,
+    pub(crate) size: u64,
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+    /// Hex-encoded SHA-256 of the content, naming the stored object.
+    pub(crate) sha256: String,
+    /// Seconds since the Unix epoch when the file was fetched, or last found
+    /// up to date with the server.
+    pub(crate) fetched: u64,
+    /// Seconds since the Unix epoch when the file was last served.
+    pub(crate) accessed: u64,
+}
+
+/// State of a cached file checked by [`GEOCache::verify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CacheState {
+    Valid,
+    /// The stored object is gone.
+    Missing,
+    /// The stored object does not match its size or SHA-256.
+    Corrupted,
+}
+
+/// A content-addressed cache of downloaded GEO files, shared across projects.
+///
+/// Files are stored once under `root/objects`, named by the SHA-256 of their
+/// content, and `root/index.json` maps each URL to its object with the size,
+/// validators (`ETag`, `Last-Modified`) and fetch time. Hits are served into
+/// the destination directory by hard link, or by copy across file systems.
+///
+/// Changes to the index and the objects are made under the lock of the cache,
+/// so that R sessions sharing it neither lose entries nor delete the objects
+/// of each other.
+pub(crate) struct GEOCache {
+    root: PathBuf,
+    /// Serializes the changes within the session.
+    lock: Mutex<()>,
+}
+
+/// Exclusive access to a [`GEOCache`], within the session by its mutex and
+/// across sessions by an advisory lock on the lock file. Both are released
+/// when dropped, the lock by closing the file, which the OS also does for
+/// crashed sessions.
+struct CacheGuard<'a> {
+    _file: File,
+    _guard: MutexGuard<'a, ()>,
+}
+
+impl GEOCache {
+    pub(crate) fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(root.join("objects"))
+            .with_context(|| format!("Failed to create cache directory {}", root.display()))?;
+        Ok(Self {
+            root,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Path of the object holding content with digest `sha256`.
+    pub(crate) fn object_path(&self, sha256: &str) -> PathBuf {
+        self.root.join("objects").join(&sha256[.. 2]).join(sha256)
+    }
+
+    /// All entries of the index.
+    pub(crate) fn entries(&self) -> Result<Vec<CacheEntry>> {
+        // the index is replaced atomically, so reading needs no lock
+        self.read_index()
+    }
+
+    /// The entry of `url`, if cached and its object still exists.
+    pub(crate) fn lookup(&self, url: &str) -> Result<Option<CacheEntry>> {
+        Ok(self
+            .read_index()?
+            .into_iter()
+            .find(|entry| entry.url == url)
+            .filter(|entry| self.object_path(&entry.sha256).is_file()))
+    }
+
+    /// Serve the cached file of `entry` into `destfile`, recording the access.
+    pub(crate) fn serve(&self, entry: &CacheEntry, destfile: &Path) -> Result<()> {
+        let guard = self.acquire()?;
+        let object = self.object_path(&entry.sha256);
+        link_or_copy(&object, destfile)
+            .with_context(|| format!("Failed to serve {} from the cache", destfile.display()))?;
+        self.modify_index(&guard, |entries| {
+            if let Some(found) = entries.iter_mut().find(|e| e.url == entry.url) {
+                found.accessed = now();
+            }
+        })
+    }
+
+    /// Record that the cached file of `url` was found up to date, restarting
+    /// its age.
+    pub(crate) fn renew(&self, url: &str) -> Result<()> {
+        let guard = self.acquire()?;
+        self.modify_index(&guard, |entries| {
+            if let Some(found) = entries.iter_mut().find(|e| e.url == url) {
+                found.fetched = now();
+            }
+        })
+    }
+
+    /// Store the downloaded `file` of `url` in the cache, replacing any
+    /// previous version.
+    pub(crate) fn store(
+        &self,
+        url: &str,
+        file: &Path,
+        validators: &Validators,
+    ) -> Result<CacheEntry> {
+        let (sha256, size) = hash_file(file)?;
+        // the object must be indexed before another session removes orphans
+        let guard = self.acquire()?;
+        let object = self.object_path(&sha256);
+        if !object.is_file() {
+            if let Some(parent) = object.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            link_or_copy(file, &object)
+                .with_context(|| format!("Failed to store {} in the cache", file.display()))?;
+        }
+        let time = now();
+        let entry = CacheEntry {
+            url: url.to_string(),
+            fname: file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            size,
+            etag: validators.etag.clone(),
+            last_modified: validators.last_modified.clone(),
+            sha256,
+            fetched: time,
+            accessed: time,
+        };
+        let stored = entry.clone();
+        self.modify_index(&guard, move |entries| {
+            entries.retain(|e| e.url != stored.url);
+            entries.push(stored);
+        })?;
+        self.remove_orphans(&guard)?;
+        Ok(entry)
+    }
+
+    /// Remove entries fetched more than `max_age` seconds ago, then the least
+    /// recently served ones until the stored objects take at most `max_size`
+    /// bytes. Returns the removed entries.
+    pub(crate) fn prune(
+        &self,
+        max_age: Option<u64>,
+        max_size: Option<u64>,
+    ) -> Result<Vec<CacheEntry>> {
+        let mut removed = Vec::new();
+        let cutoff = max_age.map(|age| now().saturating_sub(age));
+        let guard = self.acquire()?;
+        self.modify_index(&guard, |entries| {
+            let (expired, mut kept): (Vec<_>, Vec<_>) = std::mem::take(entries)
+                .into_iter()
+                .partition(|e| cutoff.is_some_and(|cutoff| e.fetched < cutoff));
+            removed.extend(expired);
+            if let Some(max_size) = max_size {
+                kept.sort_by_key(|e| std::cmp::Reverse(e.accessed));
+                let mut seen = HashSet::new();
+                let mut total = 0;
+                let (fit, over): (Vec<_>, Vec<_>) = kept.into_iter().partition(|e| {
+                    // objects shared by several URLs count once
+                    if seen.insert(e.sha256.clone()) {
+                        total += e.size;
+                    }
+                    total <= max_size
+                });
+                kept = fit;
+                removed.extend(over);
+            }
+            *entries = kept;
+        })?;
+        self.remove_orphans(&guard)?;
+        Ok(removed)
+    }
+
+    /// Check every stored object against its size and SHA-256. Entries whose
+    /// object is missing or corrupted are dropped from the index.
+    pub(crate) fn verify(&self) -> Result<Vec<(CacheEntry, CacheState)>> {
+        let mut out = Vec::new();
+        let guard = self.acquire()?;
+        self.modify_index(&guard, |entries| {
+            for entry in entries.iter() {
+                let object = self.object_path(&entry.sha256);
+                let state = match hash_file(&object) {
+                    Ok((sha256, size)) if sha256 == entry.sha256 && size == entry.size => {
+                        CacheState::Valid
+                    }
+                    Ok(_) => CacheState::Corrupted,
+                    Err(_) if !object.exists() => CacheState::Missing,
+                    Err(_) => CacheState::Corrupted,
+                };
+                out.push((entry.clone(), state));
+            }
+            entries.retain(|entry| {
+                out.iter()
+                    .any(|(e, state)| e.url == entry.url && *state == CacheState::Valid)
+            });
+        })?;
+        for (entry, state) in out.iter() {
+            if *state == CacheState::Corrupted {
+                let _ = fs::remove_file(self.object_path(&entry.sha256));
+            }
+        }
+        self.remove_orphans(&guard)?;
+        Ok(out)
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.root.join(INDEX_FILE)
+    }
+
+    fn read_index(&self) -> Result<Vec<CacheEntry>> {
+        let path = self.index_path();
+        match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("Failed to parse cache index {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Take the lock of the cache, waiting for other sessions to release it.
+    fn acquire(&self) -> Result<CacheGuard<'_>> {
+        let guard = self.lock.lock().unwrap();
+        let path = self.root.join(LOCK_FILE);
+        // the file is kept, removing it would let two sessions lock different
+        // files of the same path
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .and_then(|file| file.lock_exclusive().map(|_| file))
+            .with_context(|| format!("Failed to lock the cache {}", path.display()))?;
+        Ok(CacheGuard {
+            _file: file,
+            _guard: guard,
+        })
+    }
+
+    /// Apply `f` to the index and write it back atomically.
+    fn modify_index<F>(&self, _guard: &CacheGuard<'_>, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Vec<CacheEntry>),
+    {
+        let mut entries = self.read_index()?;
+        f(&mut entries);
+        let path = self.index_path();
+        let tmp = path.with_extension("json.tmp");
+        let bytes = serde_json::to_vec_pretty(&entries)
+            .with_context(|| "Failed to serialize the cache index")?;
+        fs::write(&tmp, bytes).with_context(|| format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Delete stored objects no longer referenced by the index.
+    fn remove_orphans(&self, _guard: &CacheGuard<'_>) -> Result<()> {
+        let used: HashSet<String> = self
+            .read_index()?
+            .into_iter()
+            .map(|entry| entry.sha256)
+            .collect();
+        for dir in fs::read_dir(self.root.join("objects"))?.flatten() {
+            if !dir.path().is_dir() {
+                continue;
+            }
+            for object in fs::read_dir(dir.path())?.flatten() {
+                let name = object.file_name().to_string_lossy().into_owned();
+                if !used.contains(&name) {
+                    let _ = fs::remove_file(object.path());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Hex-encoded SHA-256 and size of `path`.
+pub(crate) fn hash_file(path: &Path) -> Result<(String, u64)> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 1 << 16];
+    let mut size = 0;
+    loop {
+        let n = file
+            .read(&mut buf)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[.. n]);
+        size += n as u64;
+    }
+    let digest = hasher.finalize();
+    Ok((digest.iter().map(|b| format!("{:02x}", b)).collect(), size))
+}
+
+/// Hard link `from` to `to`, copying if linking is not possible (e.g. across
+/// file systems). An existing `to` is replaced.
+fn link_or_copy(from: &Path, to: &Path) -> io::Result<()> {
+    if same_file(from, to) {
+        return Ok(());
+    }
+    match fs::remove_file(to) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    if fs::hard_link(from, to).is_err() {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(_a: &Path, _b: &Path) -> bool {
+    false
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl CacheState {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            CacheState::Valid => "valid",
+            CacheState::Missing => "missing",
+            CacheState::Corrupted => "corrupted",
+        }
+    }
+}
@@ -1,79 +1,534 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
-use reqwest::Client;
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
-use tokio_stream::StreamExt;
+use reqwest::{Client, StatusCode};
+use thiserror::Error;
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
+use crate::cache::GEOCache;
 use crate::resolver::{GEOEntry, GEOResolver};
 
-struct GEODownloader {
-    urls: Vec<String>,
-    fnames: Vec<String>,
+mod ftp;
+mod http;
+mod listing;
+mod progress;
+mod replay;
+mod retry;
+mod revalidate;
+
+use ftp::{ftp_download, ftp_fetch};
+use http::http_download;
+pub(crate) use listing::{filelists_resolvers, list_resolvers, matrix_files};
+pub(crate) use progress::ProgressReport;
+use progress::{FileProgress, Progress};
+pub(crate) use replay::{send, ReplayMode};
+pub(crate) use retry::{retry_after, RetryErrorKind, RetryPolicy};
+use revalidate::is_outdated;
+pub(crate) use revalidate::{outdated_files, RevalidatePolicy};
+
+/// Errors raised while downloading a single file.
+#[derive(Debug, Error)]
+pub(crate) enum DownloadError {
+    #[error("HTTP status {status}")]
+    Status {
+        status: u16,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("FTP reply {status}: {message}")]
+    Ftp { status: u16, message: String },
+
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("Failed to connect: {0}")]
+    Connect(#[source] std::io::Error),
+
+    #[error("Connection error: {0}")]
+    Transport(#[source] std::io::Error),
+
+    #[error("Connection timed out")]
+    Timeout,
+
+    #[error("No recorded response for {method} {url} in {dir}")]
+    ReplayMiss {
+        method: String,
+        url: String,
+        dir: String,
+    },
+
+    #[error("FTP cannot be recorded or replayed, use HTTPS instead: {0}")]
+    ReplayFtp(String),
+
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl DownloadError {
+    /// HTTP status (or FTP reply) code attached to this error, if any.
+    pub(crate) fn status(&self) -> Option<u16> {
+        match self {
+            DownloadError::Status { status, .. } => Some(*status),
+            DownloadError::Ftp { status, .. } if *status > 0 => Some(*status),
+            DownloadError::Request(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+}
+
+/// Validators sent by the server with a file, identifying its version.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Validators {
+    pub(crate) etag: Option<String>,
+    pub(crate) last_modified: Option<String>,
+}
+
+/// A single file scheduled for download.
+struct DownloadTask {
+    url: String,
+    destfile: PathBuf,
+}
+
+/// A failed attempt at downloading a single file.
+pub(crate) struct DownloadAttempt {
+    pub(crate) status: Option<u16>,
+    pub(crate) error: String,
+    /// Time waited before the next attempt, `None` if no retry followed.
+    pub(crate) wait: Option<Duration>,
+}
+
+/// Outcome of downloading a single file.
+pub(crate) struct DownloadStatus {
+    pub(crate) url: String,
+    pub(crate) destfile: String,
+    pub(crate) success: bool,
+    pub(crate) status: Option<u16>,
+    pub(crate) error: Option<String>,
+    /// Whether the file was served from the cache instead of downloaded.
+    pub(crate) cached: bool,
+    /// Whether a cached copy was found outdated, so the file was downloaded
+    /// again.
+    pub(crate) outdated: bool,
+    /// All failed attempts in order, including the final one of an
+    /// unsuccessful download.
+    pub(crate) failures: Vec<DownloadAttempt>,
+}
+
+impl DownloadStatus {
+    /// Total number of attempts, including the final one.
+    pub(crate) fn attempts(&self) -> usize {
+        self.failures.len() + usize::from(self.success)
+    }
+}
+
+/// How often the progress of transfers is checked for a report.
+const PROGRESS_TICK: Duration = Duration::from_millis(100);
+
+/// Concurrent downloader for GEO files.
+///
+/// Files are collected from [`GEOResolver`]s and then fetched on a tokio
+/// runtime, with at most `concurrency` transfers in flight.
+pub(crate) struct GEODownloader {
+    tasks: Vec<DownloadTask>,
+    concurrency: usize,
+    resume: bool,
+    retry: RetryPolicy,
+    cache: Option<Arc<GEOCache>>,
+    revalidate: RevalidatePolicy,
+}
+
+/// Outcome of looking up a file in the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CacheLookup {
+    /// The cached file was served into the destination.
+    Served,
+    /// The cached file is outdated and must be downloaded again.
+    Outdated,
+    /// The file is not cached.
+    Missing,
 }
 
 impl GEODownloader {
-    fn new() -> Self {
+    pub(crate) fn new(concurrency: usize) -> Self {
         Self {
-            urls: Vec::new(),
-            fnames: Vec::new(),
+            tasks: Vec::new(),
+            concurrency: concurrency.max(1),
+            resume: true,
+            retry: RetryPolicy::default(),
+            cache: None,
+            revalidate: RevalidatePolicy::default(),
+        }
+    }
+
+    /// Whether to continue from `.part` files left by interrupted downloads
+    /// (default: true).
+    pub(crate) fn resume(&mut self, resume: bool) {
+        self.resume = resume
+    }
+
+    /// Set the policy used to retry failed downloads.
+    pub(crate) fn retry(&mut self, retry: RetryPolicy) {
+        self.retry = retry
+    }
+
+    /// Serve files already in `cache` from there, and add newly downloaded
+    /// files to it.
+    pub(crate) fn cache(&mut self, cache: GEOCache) {
+        self.cache = Some(Arc::new(cache))
+    }
+
+    /// Set when cached files are checked with the server before being served
+    /// (default: never).
+    pub(crate) fn revalidate(&mut self, revalidate: RevalidatePolicy) {
+        self.revalidate = revalidate
+    }
+
+    /// Schedule the file entry of `resolver` for download into `odir`.
+    ///
+    /// Directory entries (e.g. `matrix` and `suppl`) must be listed first, so
+    /// they are rejected here.
+    pub(crate) fn collect(&mut self, resolver: &GEOResolver, odir: &Path) -> Result<()> {
+        let entry = resolver
+            .entry()
+            .ok_or_else(|| anyhow!("No downloadable entry found"))
+            .with_context(|| format!("Invalid 'accession': {}", resolver.accession()))?;
+        match entry {
+            GEOEntry::File { url, fname } => {
+                self.push(url, odir.join(fname));
+                Ok(())
+            }
+            GEOEntry::Dir { url } => Err(anyhow!("{} is a directory", url))
+                .with_context(|| format!("Invalid 'accession': {}", resolver.accession())),
         }
     }
 
-    fn collect(&mut self, resolver: GEOResolver) -> Result<()> {
-        let entry = resolver.entry().ok_or_else(|| anyhow!("No entry found"))?;
-        if let GEOEntry::File(fname) = entry {
-            self.urls.push(resolver.url());
-            self.fnames.push(fname);
+    /// Schedule `url` for download into `destfile`.
+    pub(crate) fn push(&mut self, url: String, destfile: PathBuf) {
+        self.tasks.push(DownloadTask { url, destfile })
+    }
+
+    /// Download all collected files, returning one status per file in the
+    /// order they were collected.
+    pub(crate) fn download(self) -> Result<Vec<DownloadStatus>> {
+        let client = new_client()?;
+        let progress = Progress::new(self.tasks.iter().map(|task| task.destfile.as_path()));
+        let files = (0 .. self.tasks.len()).map(|i| progress.file(i)).collect();
+        Ok(runtime()?.block_on(self.download_all(client, files)))
+    }
+
+    /// Download all collected files like [`GEODownloader::download`], calling
+    /// `report` at most every `interval` with the progress of the transfers,
+    /// and a last time once all files are finished.
+    ///
+    /// Transfers run on the runtime workers while `report` is only ever called
+    /// from the current thread, so it may call into R.
+    pub(crate) fn download_with_progress<F>(
+        self,
+        interval: Duration,
+        mut report: F,
+    ) -> Result<Vec<DownloadStatus>>
+    where
+        F: FnMut(&ProgressReport),
+    {
+        let client = new_client()?;
+        let mut progress = Progress::new(self.tasks.iter().map(|task| task.destfile.as_path()));
+        let files = (0 .. self.tasks.len()).map(|i| progress.file(i)).collect();
+        let tick = interval.min(PROGRESS_TICK);
+        let statuses = runtime()?.block_on(async {
+            let handle = tokio::spawn(self.download_all(client, files));
+            let mut last = Instant::now();
+            report(&progress.report(false));
+            while !handle.is_finished() {
+                tokio::time::sleep(tick).await;
+                if last.elapsed() >= interval && !handle.is_finished() {
+                    report(&progress.report(false));
+                    last = Instant::now();
+                }
+            }
+            handle.await
+        });
+        let statuses = statuses.with_context(|| "Download tasks failed")?;
+        report(&progress.report(true));
+        Ok(statuses)
+    }
+
+    async fn download_all(
+        self,
+        client: Client,
+        progress: Vec<Arc<FileProgress>>,
+    ) -> Vec<DownloadStatus> {
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let mut set = JoinSet::new();
+        let n = self.tasks.len();
+        let resume = self.resume;
+        let retry = Arc::new(self.retry);
+        // the file of each task, to report it failed if the task panics
+        let mut spawned = HashMap::new();
+        for (i, (task, progress)) in self.tasks.into_iter().zip(progress).enumerate() {
+            let file = (i, task.url.clone(), task.destfile.clone(), progress.clone());
+            let client = client.clone();
+            let semaphore = semaphore.clone();
+            let retry = retry.clone();
+            let cache = self.cache.clone();
+            let revalidate = self.revalidate;
+            let handle = set.spawn(async move {
+                // The semaphore is never closed, so acquiring cannot fail.
+                let _permit = semaphore.acquire_owned().await.unwrap();
+                let lookup = serve_cached(&client, cache.as_ref(), &task, revalidate).await;
+                if lookup == CacheLookup::Served {
+                    progress.cached();
+                    return (i, DownloadStatus {
+                        url: task.url,
+                        destfile: task.destfile.to_string_lossy().into_owned(),
+                        success: true,
+                        status: None,
+                        error: None,
+                        cached: true,
+                        outdated: false,
+                        failures: Vec::new(),
+                    });
+                }
+                let (mut status, validators) =
+                    download_with_retry(&client, task, resume, &retry, &progress).await;
+                progress.finish(status.success);
+                status.outdated = lookup == CacheLookup::Outdated;
+                if let (Some(cache), Some(validators)) = (cache, validators) {
+                    store_cached(cache, &status, validators).await;
+                }
+                (i, status)
+            });
+            spawned.insert(handle.id(), file);
+        }
+
+        let mut out: Vec<Option<DownloadStatus>> = (0 .. n).map(|_| None).collect();
+        while let Some(joined) = set.join_next_with_id().await {
+            match joined {
+                Ok((_, (i, status))) => out[i] = Some(status),
+                // Tasks never panic outside of bugs, which only fail their
+                // file.
+                Err(e) => {
+                    let (i, url, destfile, progress) = spawned.remove(&e.id()).unwrap();
+                    progress.finish(false);
+                    let error = format!("Download task failed: {}", e);
+                    out[i] = Some(DownloadStatus {
+                        url,
+                        destfile: destfile.to_string_lossy().into_owned(),
+                        success: false,
+                        status: None,
+                        error: Some(error.clone()),
+                        cached: false,
+                        outdated: false,
+                        failures: vec![DownloadAttempt {
+                            status: None,
+                            error,
+                            wait: None,
+                        }],
+                    });
+                }
+            }
         }
-        Ok(())
+        out.into_iter().map(|o| o.unwrap()).collect()
     }
 }
 
-#[allow(dead_code)]
-async fn http_download(
+/// Serve `task` from `cache` if its URL was downloaded before, checking with
+/// the server first that the cached file is up to date when `revalidate` asks
+/// for it. Failing to read the cache is not an error, the file is downloaded
+/// instead, and failing to reach the server serves the cached file.
+async fn serve_cached(
     client: &Client,
-    url: &str,
-    fname: &str,
-    odir: &Path,
-) -> Result<String, String> {
-    // `HTTP_PROXY` or `http_proxy` provide HTTP proxies for HTTP connections
-    // while `HTTPS_PROXY` or `https_proxy` provide HTTPS proxies for HTTPS
-    // connections. `ALL_PROXY` or `all_proxy` provide proxies for both HTTP and
-    // HTTPS connections
+    cache: Option<&Arc<GEOCache>>,
+    task: &DownloadTask,
+    revalidate: RevalidatePolicy,
+) -> CacheLookup {
+    let Some(cache) = cache else {
+        return CacheLookup::Missing;
+    };
+    let url = task.url.clone();
+    let entry = match with_cache(cache, move |cache| cache.lookup(&url)).await {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return CacheLookup::Missing,
+        Err(e) => {
+            eprintln!("Warning: {:?}", e);
+            return CacheLookup::Missing;
+        }
+    };
+    if revalidate.is_due(&entry) {
+        match is_outdated(client, &entry).await {
+            Ok(true) => return CacheLookup::Outdated,
+            Ok(false) => {
+                let url = entry.url.clone();
+                if let Err(e) = with_cache(cache, move |cache| cache.renew(&url)).await {
+                    eprintln!("Warning: {:?}", e);
+                }
+            }
+            Err(e) => eprintln!(
+                "Warning: Failed to check whether {} is up to date, serving the cached file: {}",
+                entry.url, e
+            ),
+        }
+    }
+    let destfile = task.destfile.clone();
+    match with_cache(cache, move |cache| cache.serve(&entry, &destfile)).await {
+        Ok(()) => CacheLookup::Served,
+        Err(e) => {
+            eprintln!("Warning: {:?}", e);
+            CacheLookup::Missing
+        }
+    }
+}
 
-    // fetch and stream the data
-    let mut stream = client
-        .get(url)
-        .send()
+/// Run blocking file system operations of `cache` off the async workers.
+async fn with_cache<T, F>(cache: &Arc<GEOCache>, f: F) -> Result<T>
+where
+    F: FnOnce(&GEOCache) -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let cache = cache.clone();
+    tokio::task::spawn_blocking(move || f(&cache))
         .await
-        .with_context(|| "request error".to_string())
-        .map_err(|e| format!("{:?}", e))?
-        .error_for_status()
-        .with_context(|| format!("Failed to download from {}", url))
-        .map_err(|e| e.to_string())?
-        .bytes_stream();
-
-    // create output file
-    let ofile = odir.join(fname);
-    let mut file = File::create(&ofile)
-        .await
-        .with_context(|| format!("Failed to create {:?}", ofile))
-        .map_err(|e| format!("{:?}", e))?;
+        .with_context(|| "Cache task failed")?
+}
 
-    // write to file
-    while let Some(chunk) = stream
-        .try_next()
-        .await
-        .with_context(|| format!("Failed to download from {}", url))
-        .map_err(|e| format!("{:?}", e))?
-    {
-        file.write_all(&chunk)
-            .await
-            .with_context(|| format!("Failed to write to {:?}", ofile))
-            .map_err(|e| e.to_string())?;
+/// Add a successfully downloaded file to `cache`, warning on failure since the
+/// download itself succeeded.
+async fn store_cached(cache: Arc<GEOCache>, status: &DownloadStatus, validators: Validators) {
+    if !status.success {
+        return;
+    }
+    let url = status.url.clone();
+    let destfile = PathBuf::from(&status.destfile);
+    let stored = with_cache(&cache, move |cache| {
+        cache.store(&url, &destfile, &validators).map(|_| ())
+    })
+    .await;
+    if let Err(e) = stored {
+        eprintln!("Warning: {:?}", e);
     }
+}
+
+/// Download a single file, retrying transient failures according to `retry`.
+/// The validators of the file are returned alongside a successful status.
+async fn download_with_retry(
+    client: &Client,
+    task: DownloadTask,
+    resume: bool,
+    retry: &RetryPolicy,
+    progress: &FileProgress,
+) -> (DownloadStatus, Option<Validators>) {
+    let mut failures = Vec::new();
+    let mut attempt = 1;
+    let result = loop {
+        // Retries always continue from the `.part` file when resuming.
+        let result = if task.url.starts_with("ftp://") {
+            ftp_download(&task.url, &task.destfile, resume, progress)
+                .await
+                .map(|status| (status, Validators::default()))
+        } else {
+            http_download(client, &task.url, &task.destfile, resume, progress).await
+        };
+        match result {
+            Ok(status) => break Ok(status),
+            Err(e) if attempt < retry.max_attempts && retry.is_retryable(&e) => {
+                let wait = retry.delay(attempt, &e);
+                failures.push(DownloadAttempt {
+                    status: e.status(),
+                    error: e.to_string(),
+                    wait: Some(wait),
+                });
+                tokio::time::sleep(wait).await;
+                attempt += 1;
+            }
+            Err(e) => break Err(e),
+        }
+    };
+
+    let destfile = task.destfile.to_string_lossy().into_owned();
+    match result {
+        Ok((status, validators)) => (
+            DownloadStatus {
+                url: task.url,
+                destfile,
+                success: true,
+                status: Some(status),
+                error: None,
+                cached: false,
+                outdated: false,
+                failures,
+            },
+            Some(validators),
+        ),
+        Err(e) => {
+            failures.push(DownloadAttempt {
+                status: e.status(),
+                error: e.to_string(),
+                wait: None,
+            });
+            (
+                DownloadStatus {
+                    url: task.url,
+                    destfile,
+                    success: false,
+                    status: e.status(),
+                    error: Some(e.to_string()),
+                    cached: false,
+                    outdated: false,
+                    failures,
+                },
+                None,
+            )
+        }
+    }
+}
 
-    Ok(ofile.to_string_lossy().into_owned())
+/// Fetch a small file into memory, giving `None` if it does not exist.
+pub(crate) async fn fetch(client: &Client, url: &str) -> Result<Option<Vec<u8>>, DownloadError> {
+    if url.starts_with("ftp://") {
+        return match ftp_fetch(url).await {
+            // "File unavailable"
+            Err(DownloadError::Ftp { status: 550, .. }) => Ok(None),
+            result => result.map(Some),
+        };
+    }
+    let response = send(client.get(url)).await?;
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(None),
+        status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+        status => Err(DownloadError::Status {
+            status: status.as_u16(),
+            retry_after: None,
+        }),
+    }
+}
+
+/// Runtime driving all network access; futures are blocked on from the R
+/// main thread.
+pub(crate) fn runtime() -> Result<Runtime> {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .with_context(|| "Failed to create tokio runtime")
+}
+
+pub(crate) fn new_client() -> Result<Client> {
+    // `HTTP_PROXY` or `http_proxy` provide HTTP proxies for HTTP connections
+    // while `HTTPS_PROXY` or `https_proxy` provide HTTPS proxies for HTTPS
+    // connections. `ALL_PROXY` or `all_proxy` provide proxies for both HTTP and
+    // HTTPS connections
+    Client::builder()
+        .user_agent(concat!("geokit/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(60))
+        // fail stalled transfers, so they can be retried, without limiting
+        // the total time of large downloads
+        .read_timeout(Duration::from_secs(60))
+        .build()
+        .with_context(|| "Failed to create HTTP client")
 }
@@ -0,0 +1,485 @@
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+use regex::Regex;
+use reqwest::Url;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+
+use super::http::part_path;
+use super::listing::DirEntry;
+use super::progress::FileProgress;
+use super::replay::ensure_live;
+use super::DownloadError;
+
+/// Timeout for establishing control and data connections.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Timeout for a single read on control and data connections, so that a
+/// stalled server fails the transfer instead of hanging it.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A reply on the FTP control connection.
+struct Reply {
+    code: u16,
+    message: String,
+}
+
+/// Minimal FTP client for anonymous, passive-mode, binary transfers.
+///
+/// Only the commands needed to download and list files from the GEO FTP site
+/// are implemented. Data connections always use passive mode (`EPSV`, falling
+/// back to `PASV`), which is what firewalls usually allow.
+struct FtpClient {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    peer: SocketAddr,
+}
+
+impl FtpClient {
+    /// Connect to the server of `url` and log in anonymously.
+    async fn connect(url: &Url) -> Result<Self, DownloadError> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| DownloadError::InvalidUrl(url.to_string()))?;
+        let port = url.port().unwrap_or(21);
+        let stream = connect_tcp((host, port)).await?;
+        let peer = stream.peer_addr().map_err(DownloadError::Connect)?;
+        let (reader, writer) = stream.into_split();
+        let mut client = Self {
+            reader: BufReader::new(reader),
+            writer,
+            peer,
+        };
+        client.read_reply(&[220]).await?;
+
+        let user = match url.username() {
+            "" => "anonymous",
+            user => user,
+        };
+        let reply = client
+            .command(&format!("USER {}", user), &[230, 331])
+            .await?;
+        if reply.code == 331 {
+            let pass = url.password().unwrap_or("anonymous@");
+            client
+                .command(&format!("PASS {}", pass), &[230, 202])
+                .await?;
+        }
+        // Binary mode, files must never be altered in transit.
+        client.command("TYPE I", &[200]).await?;
+        Ok(client)
+    }
+
+    /// Send `cmd` and wait for a reply with one of the `expected` codes.
+    async fn command(&mut self, cmd: &str, expected: &[u16]) -> Result<Reply, DownloadError> {
+        self.writer
+            .write_all(format!("{}\r\n", cmd).as_bytes())
+            .await
+            .map_err(DownloadError::Transport)?;
+        self.read_reply(expected).await
+    }
+
+    /// Read a (possibly multi-line) reply from the control connection.
+    async fn read_reply(&mut self, expected: &[u16]) -> Result<Reply, DownloadError> {
+        let mut line = String::new();
+        let mut message = String::new();
+        let code = loop {
+            line.clear();
+            let n = timed_read(self.reader.read_line(&mut line)).await?;
+            if n == 0 {
+                return Err(DownloadError::Transport(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "FTP control connection closed",
+                )));
+            }
+            message.push_str(line.trim_end());
+            message.push('\n');
+            // The last line of a reply is "ddd <text>"; continuation lines
+            // start with "ddd-" or anything else.
+            let bytes = line.as_bytes();
+            if bytes.len() >= 4 && bytes[.. 3].iter().all(u8::is_ascii_digit) && bytes[3] == b' ' {
+                break line[.. 3].parse::<u16>().unwrap();
+            }
+            if bytes.len() == 5 && bytes[.. 3].iter().all(u8::is_ascii_digit) {
+                // "ddd\r\n" without text
+                break line[.. 3].parse::<u16>().unwrap();
+            }
+        };
+        let reply = Reply {
+            code,
+            message: message.trim_end().to_string(),
+        };
+        if expected.contains(&reply.code) {
+            Ok(reply)
+        } else {
+            Err(DownloadError::Ftp {
+                status: reply.code,
+                message: reply.message,
+            })
+        }
+    }
+
+    /// Open a passive data connection.
+    async fn passive(&mut self) -> Result<TcpStream, DownloadError> {
+        let port = match self.command("EPSV", &[229]).await {
+            Ok(reply) => parse_epsv(&reply.message),
+            Err(DownloadError::Ftp { .. }) => {
+                let reply = self.command("PASV", &[227]).await?;
+                parse_pasv(&reply.message)
+            }
+            Err(e) => return Err(e),
+        };
+        let port = port.ok_or_else(|| DownloadError::Ftp {
+            status: 0,
+            message: "Failed to parse passive mode reply".to_string(),
+        })?;
+        // The address in PASV replies is often a private one behind NAT, so
+        // always connect back to the control connection peer.
+        connect_tcp(SocketAddr::new(self.peer.ip(), port)).await
+    }
+
+    /// Size of the remote file, if the server supports `SIZE`.
+    async fn size(&mut self, path: &str) -> Option<u64> {
+        let reply = self.command(&format!("SIZE {}", path), &[213]).await.ok()?;
+        reply.message.get(4 ..)?.trim().parse().ok()
+    }
+
+    /// Read the listing sent by `cmd` (`MLSD`, `LIST` or `NLST`) for `path`.
+    async fn listing(&mut self, cmd: &str, path: &str) -> Result<String, DownloadError> {
+        let mut data = self.passive().await?;
+        self.command(&format!("{} {}", cmd, path), &[125, 150])
+            .await?;
+        let bytes = read_all(&mut data).await?;
+        drop(data);
+        self.read_reply(&[226, 250]).await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    async fn quit(mut self) {
+        let _ = self.command("QUIT", &[221]).await;
+    }
+}
+
+async fn connect_tcp<A: tokio::net::ToSocketAddrs>(addr: A) -> Result<TcpStream, DownloadError> {
+    match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await {
+        Ok(stream) => stream.map_err(DownloadError::Connect),
+        Err(_) => Err(DownloadError::Timeout),
+    }
+}
+
+/// Run a read on a control or data connection, failing with
+/// [`DownloadError::Timeout`] if no data arrives within [`READ_TIMEOUT`].
+async fn timed_read<F>(read: F) -> Result<usize, DownloadError>
+where
+    F: std::future::Future<Output = std::io::Result<usize>>,
+{
+    match tokio::time::timeout(READ_TIMEOUT, read).await {
+        Ok(n) => n.map_err(DownloadError::Transport),
+        Err(_) => Err(DownloadError::Timeout),
+    }
+}
+
+/// Read a data connection until the server closes it.
+async fn read_all(data: &mut TcpStream) -> Result<Vec<u8>, DownloadError> {
+    let mut bytes = Vec::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        match timed_read(data.read(&mut buf)).await? {
+            0 => return Ok(bytes),
+            n => bytes.extend_from_slice(&buf[.. n]),
+        }
+    }
+}
+
+/// Download the `ftp://` `url` into `ofile`, returning the final FTP reply
+/// code.
+///
+/// Like [`http_download`](super::http::http_download), bytes go to a `.part`
+/// file first. With `resume`, an existing `.part` file is continued with
+/// `REST`; servers rejecting `REST` get the whole file again, as do `.part`
+/// files larger than the size given by `SIZE`. A transfer ending short of (or
+/// beyond) that size fails, keeping the `.part` file to be resumed. Bytes
+/// received are counted in `progress`, out of that size.
+pub(super) async fn ftp_download(
+    url: &str,
+    ofile: &Path,
+    resume: bool,
+    progress: &FileProgress,
+) -> Result<u16, DownloadError> {
+    ensure_live(url)?;
+    let url = Url::parse(url).map_err(|_| DownloadError::InvalidUrl(url.to_string()))?;
+    let path = url.path();
+    let part = part_path(ofile);
+    let mut offset = if resume {
+        fs::metadata(&part).await.map(|m| m.len()).unwrap_or(0)
+    } else {
+        0
+    };
+
+    let mut client = FtpClient::connect(&url).await?;
+    let size = client.size(path).await;
+    if size.is_some_and(|size| offset > size) {
+        // The `.part` file cannot be of this version of the file.
+        offset = 0;
+    }
+    if offset > 0 && size == Some(offset) {
+        // The `.part` file already holds the whole file.
+        progress.start(offset, size);
+        client.quit().await;
+        fs::rename(&part, ofile).await?;
+        return Ok(226);
+    }
+
+    let mut data = client.passive().await?;
+    if offset > 0
+        && client
+            .command(&format!("REST {}", offset), &[350])
+            .await
+            .is_err()
+    {
+        offset = 0;
+    }
+    client
+        .command(&format!("RETR {}", path), &[125, 150])
+        .await?;
+    progress.start(offset, size);
+
+    let mut file = if offset > 0 {
+        OpenOptions::new().append(true).open(&part).await?
+    } else {
+        File::create(&part).await?
+    };
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut len = offset;
+    loop {
+        let n = timed_read(data.read(&mut buf)).await?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[.. n]).await?;
+        len += n as u64;
+        progress.advance(n as u64);
+    }
+    file.flush().await?;
+    drop(file);
+    drop(data);
+
+    let reply = client.read_reply(&[226, 250]).await?;
+    client.quit().await;
+    if let Some(size) = size.filter(|size| *size != len) {
+        return Err(DownloadError::Transport(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!("Received {} of {} bytes", len, size),
+        )));
+    }
+    fs::rename(&part, ofile).await?;
+    Ok(reply.code)
+}
+
+/// Fetch the `ftp://` `url` into memory.
+pub(super) async fn ftp_fetch(url: &str) -> Result<Vec<u8>, DownloadError> {
+    ensure_live(url)?;
+    let url = Url::parse(url).map_err(|_| DownloadError::InvalidUrl(url.to_string()))?;
+    let mut client = FtpClient::connect(&url).await?;
+    let mut data = client.passive().await?;
+    client
+        .command(&format!("RETR {}", url.path()), &[125, 150])
+        .await?;
+    let bytes = read_all(&mut data).await?;
+    drop(data);
+    client.read_reply(&[226, 250]).await?;
+    client.quit().await;
+    Ok(bytes)
+}
+
+/// List the `ftp://` directory `url`.
+///
+/// `MLSD` is preferred since it gives machine-readable facts; servers without
+/// it are listed with `LIST` (Unix `ls -l` style) and, as a last resort,
+/// `NLST` which only reports names.
+pub(super) async fn ftp_list(url: &str) -> Result<Vec<DirEntry>, DownloadError> {
+    ensure_live(url)?;
+    let parsed = Url::parse(url).map_err(|_| DownloadError::InvalidUrl(url.to_string()))?;
+    let path = parsed.path();
+    let base = url.trim_end_matches('/');
+    let mut client = FtpClient::connect(&parsed).await?;
+    let entries = match client.listing("MLSD", path).await {
+        Ok(text) => parse_mlsd(base, &text),
+        Err(DownloadError::Ftp { .. }) => match client.listing("LIST", path).await {
+            Ok(text) => parse_list(base, &text),
+            Err(DownloadError::Ftp { .. }) => {
+                parse_nlst(base, &client.listing("NLST", path).await?)
+            }
+            Err(e) => return Err(e),
+        },
+        Err(e) => return Err(e),
+    };
+    client.quit().await;
+    Ok(entries)
+}
+
+/// Parse `229 Entering Extended Passive Mode (|||port|)`.
+fn parse_epsv(message: &str) -> Option<u16> {
+    let start = message.find('(')?;
+    let end = message[start ..].find(')')? + start;
+    let inner = &message[start + 1 .. end];
+    let delim = inner.chars().next()?;
+    inner.split(delim).nth(3)?.parse().ok()
+}
+
+/// Parse `227 Entering Passive Mode (h1,h2,h3,h4,p1,p2)`.
+fn parse_pasv(message: &str) -> Option<u16> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let regex = RE.get_or_init(|| {
+        Regex::new(r"(\d+),(\d+),(\d+),(\d+),(\d+),(\d+)").expect("Failed to create regex")
+    });
+    let caps = regex.captures(message)?;
+    // each part is a byte, malformed replies are rejected
+    let p1: u8 = caps.get(5)?.as_str().parse().ok()?;
+    let p2: u8 = caps.get(6)?.as_str().parse().ok()?;
+    Some(u16::from_be_bytes([p1, p2]))
+}
+
+/// Parse `MLSD` lines such as
+/// `type=file;size=1024;modify=20080312120900; GSE1000_RAW.tar`.
+fn parse_mlsd(base: &str, text: &str) -> Vec<DirEntry> {
+    text.lines()
+        .filter_map(|line| {
+            let (facts, name) = line.split_once(' ')?;
+            let name = name.trim();
+            let mut is_dir = false;
+            let mut size = None;
+            let mut modified = None;
+            for fact in facts.split(';') {
+                let Some((key, value)) = fact.split_once('=') else {
+                    continue;
+                };
+                match key.to_ascii_lowercase().as_str() {
+                    "type" => match value.to_ascii_lowercase().as_str() {
+                        // Skip the listed directory itself and its parent.
+                        "cdir" | "pdir" => return None,
+                        "dir" => is_dir = true,
+                        _ => {}
+                    },
+                    "size" => size = value.parse().ok(),
+                    "modify" if value.len() >= 12 => {
+                        modified = Some(format!(
+                            "{}-{}-{} {}:{}",
+                            &value[0 .. 4],
+                            &value[4 .. 6],
+                            &value[6 .. 8],
+                            &value[8 .. 10],
+                            &value[10 .. 12]
+                        ))
+                    }
+                    _ => {}
+                }
+            }
+            Some(new_entry(base, name, size, modified, is_dir))
+        })
+        .collect()
+}
+
+/// Parse Unix-style `LIST` lines such as
+/// `-r--r--r--   1 ftp anonymous  14680064 Mar 12  2008 GSE1000_RAW.tar`.
+fn parse_list(base: &str, text: &str) -> Vec<DirEntry> {
+    let today = today();
+    text.lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 9 {
+                return None;
+            }
+            let is_dir = fields[0].starts_with('d');
+            let name = fields[8 ..].join(" ");
+            // Symbolic links are listed as "name -> target".
+            let name = match name.split_once(" -> ") {
+                Some((name, _)) => name.to_string(),
+                None => name,
+            };
+            if name == "." || name == ".." {
+                return None;
+            }
+            let size = if is_dir { None } else { fields[4].parse().ok() };
+            let modified = today.and_then(|today| list_date(&fields[5 .. 8], today));
+            Some(new_entry(base, &name, size, modified, is_dir))
+        })
+        .collect()
+}
+
+/// Convert the date of a `LIST` line, `Mar 12  2008`, or `Mar 12 14:03` for
+/// files modified within the last six months, to `YYYY-MM-DD HH:MM`. The year
+/// left out by the latter is the one putting the date at most `today`.
+fn list_date(fields: &[&str], today: (i32, u32, u32)) -> Option<String> {
+    let [month, day, year_or_time] = fields else {
+        return None;
+    };
+    let month = month_number(month)?;
+    let day: u32 = day.parse().ok().filter(|day| (1 ..= 31).contains(day))?;
+    let (year, time) = match year_or_time.split_once(':') {
+        Some((hour, minute)) => {
+            let hour: u32 = hour.parse().ok().filter(|hour| *hour < 24)?;
+            let minute: u32 = minute.parse().ok().filter(|minute| *minute < 60)?;
+            let (year, today_month, today_day) = today;
+            let year = if (month, day) > (today_month, today_day) {
+                year - 1
+            } else {
+                year
+            };
+            (year, format!("{:02}:{:02}", hour, minute))
+        }
+        None => (year_or_time.parse().ok()?, "00:00".to_string()),
+    };
+    Some(format!("{:04}-{:02}-{:02} {}", year, month, day, time))
+}
+
+/// Month number of an English month abbreviation (`Jan` is 1).
+fn month_number(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let i = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(name))?;
+    Some(i as u32 + 1)
+}
+
+/// Today's year, month and day in UTC, read from the HTTP-date of now
+/// (e.g. `Sun, 18 Oct 2026 08:00:00 GMT`).
+fn today() -> Option<(i32, u32, u32)> {
+    let now = httpdate::fmt_http_date(SystemTime::now());
+    let fields: Vec<&str> = now.split_whitespace().collect();
+    Some((
+        fields.get(3)?.parse().ok()?,
+        month_number(fields.get(2)?)?,
+        fields.get(1)?.parse().ok()?,
+    ))
+}
+
+/// Parse `NLST` output, one name (or path) per line.
+fn parse_nlst(base: &str, text: &str) -> Vec<DirEntry> {
+    text.lines()
+        .map(|line| line.trim().rsplit('/').next().unwrap_or_default())
+        .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+        .map(|name| new_entry(base, name, None, None, false))
+        .collect()
+}
+
+fn new_entry(
+    base: &str,
+    name: &str,
+    size: Option<u64>,
+    modified: Option<String>,
+    is_dir: bool,
+) -> DirEntry {
+    DirEntry {
+        name: name.to_string(),
+        url: format!("{}/{}", base, name),
+        size,
+        modified,
+        is_dir,
+    }
+}
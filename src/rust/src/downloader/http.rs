@@ -0,0 +1,148 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use reqwest::header::{HeaderMap, ACCEPT_RANGES, CONTENT_RANGE, ETAG, LAST_MODIFIED, RANGE};
+use reqwest::{Client, StatusCode};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio_stream::StreamExt;
+
+use super::progress::FileProgress;
+use super::replay::send;
+use super::retry::retry_after;
+use super::{DownloadError, Validators};
+
+/// Path of the temporary file holding the bytes received so far.
+pub(super) fn part_path(ofile: &Path) -> PathBuf {
+    let mut name = OsString::from(ofile.as_os_str());
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+/// Stream `url` into `ofile`, returning the HTTP status code and the
+/// validators of the file on success.
+///
+/// Bytes are written to a `.part` file next to `ofile`, which is renamed once
+/// the transfer completes. With `resume`, an existing `.part` file is continued
+/// with a `Range` request; if the server ignores the range or answers with a
+/// mismatched `Content-Range`, the file is downloaded again from scratch.
+/// Bytes received are counted in `progress`, out of the `Content-Length`.
+pub(super) async fn http_download(
+    client: &Client,
+    url: &str,
+    ofile: &Path,
+    resume: bool,
+    progress: &FileProgress,
+) -> Result<(u16, Validators), DownloadError> {
+    let part = part_path(ofile);
+    let mut offset = if resume {
+        fs::metadata(&part).await.map(|m| m.len()).unwrap_or(0)
+    } else {
+        0
+    };
+
+    // At most two rounds: a ranged request, then a full one if the range was
+    // not honoured.
+    let (response, append) = loop {
+        let mut request = client.get(url);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        }
+        let response = send(request).await?;
+        let status = response.status();
+        match status {
+            StatusCode::PARTIAL_CONTENT if offset > 0 => {
+                let range = content_range(response.headers());
+                if range.is_some_and(|(start, _)| start == Some(offset)) {
+                    break (response, true);
+                }
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                // The `.part` file may already hold the whole file.
+                let range = content_range(response.headers());
+                if range.is_some_and(|(_, total)| total == Some(offset)) {
+                    progress.start(offset, Some(offset));
+                    fs::rename(&part, ofile).await?;
+                    return Ok((status.as_u16(), Validators::default()));
+                }
+            }
+            s if s.is_success() => break (response, false),
+            s => {
+                return Err(DownloadError::Status {
+                    status: s.as_u16(),
+                    retry_after: retry_after(response.headers()),
+                });
+            }
+        }
+        // The partial content cannot be trusted: start over.
+        let _ = fs::remove_file(&part).await;
+        offset = 0;
+    };
+
+    let status = response.status();
+    let validators = validators(response.headers());
+    let accept_ranges = response
+        .headers()
+        .get(ACCEPT_RANGES)
+        .and_then(|v| v.to_str().ok())
+        .map_or(true, |v| !v.eq_ignore_ascii_case("none"));
+    let offset = if append { offset } else { 0 };
+    progress.start(offset, response.content_length().map(|len| offset + len));
+
+    let result = async {
+        let mut file = if append {
+            OpenOptions::new().append(true).open(&part).await?
+        } else {
+            File::create(&part).await?
+        };
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.try_next().await? {
+            file.write_all(&chunk).await?;
+            progress.advance(chunk.len() as u64);
+        }
+        file.flush().await?;
+        Ok::<(), DownloadError>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => {
+            fs::rename(&part, ofile).await?;
+            Ok((status.as_u16(), validators))
+        }
+        Err(e) => {
+            // Keep the `.part` file only if it can be resumed later.
+            if !accept_ranges {
+                let _ = fs::remove_file(&part).await;
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Read the `ETag` and `Last-Modified` headers of a response.
+pub(super) fn validators(headers: &HeaderMap) -> Validators {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    Validators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    }
+}
+
+/// Parse `Content-Range: bytes <start>-<end>/<total>` (or `bytes */<total>`)
+/// into the start offset and total size.
+fn content_range(headers: &HeaderMap) -> Option<(Option<u64>, Option<u64>)> {
+    let value = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let value = value.trim().strip_prefix("bytes")?.trim_start();
+    let (range, total) = value.split_once('/')?;
+    let start = range
+        .split_once('-')
+        .and_then(|(start, _)| start.trim().parse().ok());
+    let total = total.trim().parse().ok();
+    Some((start, total))
+}
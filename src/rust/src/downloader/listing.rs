@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use reqwest::Client;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::ftp::ftp_list;
+use super::{fetch, new_client, retry_after, runtime, send, DownloadError, RetryPolicy};
+use crate::parser::filelist::{parse_filelist, FileListRecord};
+use crate::resolver::{rnaseq_file_url, GEOEntry, GEORNASeqFormat, GEOResolver};
+
+/// Maximal number of directories listed at a time, to stay within the request
+/// rate accepted by NCBI.
+const LISTING_CONCURRENCY: usize = 5;
+
+/// A file or sub-directory found in a GEO FTP directory.
+pub(crate) struct DirEntry {
+    pub(crate) name: String,
+    pub(crate) url: String,
+    /// Size in bytes. Listings over HTTPS only report rounded sizes
+    /// (e.g. `14M`), so this is approximate for large files.
+    pub(crate) size: Option<u64>,
+    /// Last modification time as reported by the server (`YYYY-MM-DD HH:MM`).
+    pub(crate) modified: Option<String>,
+    pub(crate) is_dir: bool,
+}
+
+/// List the directory entries of all `resolvers` concurrently, returning one
+/// listing per resolver in the same order.
+///
+/// With `recursive`, sub-directories are listed as well, and the names of
+/// their entries are prefixed with the sub-directory path (e.g. `tar/x.gz`).
+/// Fails if any resolver points to a single file rather than a directory.
+///
+/// For the NCBI-generated RNA-seq formats, the files of the requested table
+/// offered on the download page are listed, so an empty listing means the
+/// table is not available for the Series.
+pub(crate) fn list_resolvers(
+    resolvers: &[GEOResolver],
+    recursive: bool,
+) -> Result<Vec<Vec<DirEntry>>> {
+    let rnaseq: HashMap<String, (String, GEORNASeqFormat)> = resolvers
+        .iter()
+        .filter_map(|resolver| {
+            let format = resolver.rnaseq_format()?;
+            Some((resolver.url(), (resolver.accession().to_string(), format)))
+        })
+        .collect();
+    on_dirs(resolvers, move |client, url| {
+        let rnaseq = rnaseq.get(&url).cloned();
+        async move {
+            match rnaseq {
+                Some((accession, format)) => list_rnaseq(&client, &url, &accession, format).await,
+                None => list_tree(&client, &url, recursive).await,
+            }
+        }
+    })
+}
+
+/// A Series Matrix file found in the `matrix` directory of a Series.
+pub(crate) struct MatrixFile {
+    pub(crate) name: String,
+    pub(crate) url: String,
+    pub(crate) size: Option<u64>,
+    /// Platform of the file for Series with several platforms, whose files are
+    /// suffixed with it (`GSEnnn-GPLxxx_series_matrix.txt.gz`), `None` for
+    /// single-platform Series (`GSEnnn_series_matrix.txt.gz`).
+    pub(crate) platform: Option<String>,
+}
+
+/// List the Series Matrix files of all `resolvers` concurrently, ordered by
+/// platform number.
+///
+/// With `platform`, only the files of this platform are kept. Files of
+/// single-platform Series carry no platform and are always kept, since
+/// their platform is only known once parsed. Fails if a multi-platform
+/// Series has no file for `platform`.
+pub(crate) fn matrix_files(
+    resolvers: &[GEOResolver],
+    platform: Option<&str>,
+) -> Result<Vec<Vec<MatrixFile>>> {
+    let listings = on_dirs(resolvers, |client, url| async move {
+        list_dir(&client, &url).await
+    })?;
+    resolvers
+        .iter()
+        .zip(listings)
+        .map(|(resolver, entries)| {
+            let mut files: Vec<MatrixFile> = entries
+                .into_iter()
+                .filter(|entry| !entry.is_dir)
+                .filter_map(|entry| {
+                    let platform = matrix_platform(resolver.accession(), &entry.name)?;
+                    Some(MatrixFile {
+                        name: entry.name,
+                        url: entry.url,
+                        size: entry.size,
+                        platform,
+                    })
+                })
+                .collect();
+            files.sort_by_key(|file| {
+                file.platform
+                    .as_deref()
+                    .and_then(|gpl| gpl[3 ..].parse::<u64>().ok())
+            });
+            let Some(platform) = platform else {
+                return Ok(files);
+            };
+            if files.iter().all(|file| file.platform.is_none()) {
+                return Ok(files);
+            }
+            let available = files
+                .iter()
+                .filter_map(|file| file.platform.as_deref())
+                .collect::<Vec<_>>()
+                .join(", ");
+            files.retain(|file| {
+                file.platform
+                    .as_deref()
+                    .is_some_and(|gpl| gpl.eq_ignore_ascii_case(platform))
+            });
+            if files.is_empty() {
+                return Err(anyhow!(
+                    "No Series Matrix file of {} for {} (available: {})",
+                    platform,
+                    resolver.accession(),
+                    available
+                ));
+            }
+            Ok(files)
+        })
+        .collect()
+}
+
+/// Classify `fname`, a file of the `matrix` directory of `accession`.
+///
+/// Returns `None` if it is not a Series Matrix file of `accession`, or else
+/// the platform it is suffixed with, if any.
+fn matrix_platform(accession: &str, fname: &str) -> Option<Option<String>> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let regex = RE.get_or_init(|| {
+        Regex::new(r"(?i)^(GSE\d+)(?:-(GPL\d+))?_series_matrix\.txt(?:\.gz)?$")
+            .with_context(|| "Failed to create regex")
+            .unwrap()
+    });
+    let caps = regex.captures(fname)?;
+    if !caps[1].eq_ignore_ascii_case(accession) {
+        return None;
+    }
+    Some(caps.get(2).map(|gpl| gpl.as_str().to_ascii_uppercase()))
+}
+
+/// Fetch and parse the `filelist.txt` of all `resolvers` concurrently.
+///
+/// Directories without `filelist.txt` give an empty listing.
+pub(crate) fn filelists_resolvers(resolvers: &[GEOResolver]) -> Result<Vec<Vec<FileListRecord>>> {
+    on_dirs(resolvers, |client, url| async move {
+        let url = format!("{}/filelist.txt", url.trim_end_matches('/'));
+        let bytes = fetch(&client, &url)
+            .await
+            .with_context(|| format!("Failed to fetch {}", url))?;
+        match bytes {
+            Some(bytes) => parse_filelist(&String::from_utf8_lossy(&bytes))
+                .with_context(|| format!("Failed to parse {}", url)),
+            None => Ok(Vec::new()),
+        }
+    })
+}
+
+/// Run `job` on the directory URL of every resolver concurrently, returning
+/// the results in the same order as `resolvers`. Failures name the directory
+/// and the accession it belongs to.
+///
+/// At most [`LISTING_CONCURRENCY`] jobs run at a time, and jobs failing with
+/// a transient [`DownloadError`] are run again under the default
+/// [`RetryPolicy`].
+fn on_dirs<T, F, Fut>(resolvers: &[GEOResolver], job: F) -> Result<Vec<T>>
+where
+    F: Fn(Client, String) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    let dirs = resolvers
+        .iter()
+        .map(|resolver| match resolver.entry() {
+            Some(GEOEntry::Dir { url }) => Ok((url, resolver.accession().to_string())),
+            _ => Err(anyhow!("{} is not a directory", resolver.url()))
+                .with_context(|| format!("Invalid 'accession': {}", resolver.accession())),
+        })
+        .collect::<Result<Vec<(String, String)>>>()?;
+    let client = new_client()?;
+    let job = Arc::new(job);
+    let semaphore = Arc::new(Semaphore::new(LISTING_CONCURRENCY));
+    let retry = Arc::new(RetryPolicy::default());
+    runtime()?.block_on(async move {
+        let mut set = JoinSet::new();
+        for (i, (url, accession)) in dirs.into_iter().enumerate() {
+            let client = client.clone();
+            let job = job.clone();
+            let semaphore = semaphore.clone();
+            let retry = retry.clone();
+            set.spawn(async move {
+                // The semaphore is never closed, so acquiring cannot fail.
+                let _permit = semaphore.acquire_owned().await.unwrap();
+                let mut attempt = 1;
+                let result = loop {
+                    let result = job(client.clone(), url.clone()).await;
+                    let wait = match &result {
+                        Err(e) if attempt < retry.max_attempts => e
+                            .chain()
+                            .find_map(|cause| cause.downcast_ref::<DownloadError>())
+                            .filter(|cause| retry.is_retryable(cause))
+                            .map(|cause| retry.delay(attempt, cause)),
+                        _ => None,
+                    };
+                    match wait {
+                        Some(wait) => tokio::time::sleep(wait).await,
+                        None => break result,
+                    }
+                    attempt += 1;
+                };
+                let result =
+                    result.with_context(|| format!("Cannot open {} for {}", url, accession));
+                (i, result)
+            });
+        }
+        let mut out: Vec<Option<T>> = (0 .. set.len()).map(|_| None).collect();
+        while let Some(joined) = set.join_next().await {
+            let (i, result) = joined.expect("listing task panicked");
+            out[i] = Some(result?);
+        }
+        Ok(out.into_iter().map(|o| o.unwrap()).collect())
+    })
+}
+
+/// List `url`, descending into sub-directories if `recursive`.
+async fn list_tree(client: &Client, url: &str, recursive: bool) -> Result<Vec<DirEntry>> {
+    let mut out = Vec::new();
+    let mut pending = vec![(String::new(), url.to_string())];
+    while let Some((prefix, url)) = pending.pop() {
+        for mut entry in list_dir(client, &url).await? {
+            if !prefix.is_empty() {
+                entry.name = format!("{}/{}", prefix, entry.name);
+            }
+            if recursive && entry.is_dir {
+                pending.push((entry.name.clone(), entry.url.clone()));
+            }
+            out.push(entry);
+        }
+    }
+    Ok(out)
+}
+
+/// List the files of the RNA-seq table `format` on the download page `url`
+/// of `accession`.
+async fn list_rnaseq(
+    client: &Client,
+    url: &str,
+    accession: &str,
+    format: GEORNASeqFormat,
+) -> Result<Vec<DirEntry>> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let regex = RE.get_or_init(|| {
+        Regex::new(r#"[?&]file=([^"'&\s<>]+)"#)
+            .with_context(|| "Failed to create regex")
+            .unwrap()
+    });
+    let html = fetch_text(client, url)
+        .await
+        .with_context(|| format!("Failed to fetch {}", url))?
+        .replace("&amp;", "&");
+    let mut out: Vec<DirEntry> = Vec::new();
+    for caps in regex.captures_iter(&html) {
+        let name = &caps[1];
+        if !format.matches(accession, name) || out.iter().any(|entry| entry.name == name) {
+            continue;
+        }
+        out.push(DirEntry {
+            name: name.to_string(),
+            url: rnaseq_file_url(accession, name),
+            size: None,
+            modified: None,
+            is_dir: false,
+        });
+    }
+    Ok(out)
+}
+
+/// Fetch and parse the directory listing at `url`, over HTTPS or FTP.
+pub(crate) async fn list_dir(client: &Client, url: &str) -> Result<Vec<DirEntry>> {
+    if url.starts_with("ftp://") {
+        return ftp_list(url)
+            .await
+            .with_context(|| format!("Failed to list {}", url));
+    }
+    // Relative links in the index page resolve against the trailing slash.
+    let url = if url.ends_with('/') {
+        url.to_string()
+    } else {
+        format!("{}/", url)
+    };
+    let html = fetch_text(client, &url)
+        .await
+        .with_context(|| format!("Failed to list {}", url))?;
+    Ok(parse_html_index(&url, &html))
+}
+
+/// Fetch the page at `url` as text, failing on unsuccessful statuses.
+async fn fetch_text(client: &Client, url: &str) -> Result<String, DownloadError> {
+    let response = send(client.get(url)).await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(DownloadError::Status {
+            status: status.as_u16(),
+            retry_after: retry_after(response.headers()),
+        });
+    }
+    Ok(response.text().await?)
+}
+
+/// Parse an Apache-style index page, as served by
+/// <https://ftp.ncbi.nlm.nih.gov/geo>, into directory entries.
+///
+/// Each entry looks like:
+/// ```text
+/// <a href="GSE1000_RAW.tar">GSE1000_RAW.tar</a>   2008-03-12 12:09   14M
+/// ```
+/// Links to the parent directory and column-sorting links are skipped.
+pub(crate) fn parse_html_index(url: &str, html: &str) -> Vec<DirEntry> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let regex = RE.get_or_init(|| {
+        Regex::new(
+            r#"(?i)<a\s+href="([^"]+)"[^>]*>[^<]*</a>\s*(\d{4}-\d{2}-\d{2}\s+\d{2}:\d{2})?\s*([\d.]+[KMGTP]?|-)?"#,
+        )
+        .with_context(|| "Failed to create regex")
+        .unwrap()
+    });
+    let base = url.trim_end_matches('/');
+    regex
+        .captures_iter(html)
+        .filter_map(|caps| {
+            let href = caps.get(1)?.as_str();
+            // Skip sorting links ("?C=N;O=D"), absolute links (the parent
+            // directory) and anything pointing outside this directory.
+            if href.starts_with('?')
+                || href.starts_with('/')
+                || href.starts_with("..")
+                || href.contains("://")
+            {
+                return None;
+            }
+            let is_dir = href.ends_with('/');
+            let name = href.trim_end_matches('/').to_string();
+            Some(DirEntry {
+                url: format!("{}/{}", base, name),
+                name,
+                size: caps.get(3).and_then(|m| parse_size(m.as_str())),
+                modified: caps
+                    .get(2)
+                    .map(|m| m.as_str().split_whitespace().collect::<Vec<_>>().join(" ")),
+                is_dir,
+            })
+        })
+        .collect()
+}
+
+/// Parse human-readable sizes such as `512`, `1.0K` or `14M` into bytes.
+fn parse_size(size: &str) -> Option<u64> {
+    let (number, unit) = match size.char_indices().last()? {
+        (i, c) if c.is_ascii_alphabetic() => (&size[.. i], c.to_ascii_uppercase()),
+        _ => (size, 'B'),
+    };
+    let number: f64 = number.parse().ok()?;
+    let exp = match unit {
+        'B' => 0,
+        'K' => 1,
+        'M' => 2,
+        'G' => 3,
+        'T' => 4,
+        'P' => 5,
+        _ => return None,
+    };
+    Some((number * 1024f64.powi(exp)).round() as u64)
+}
@@ -0,0 +1,266 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// State of a single file in a [`ProgressReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileState {
+    Pending,
+    Active,
+    Done,
+    /// Served from the cache, without transfer.
+    Cached,
+    Failed,
+}
+
+impl FileState {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            FileState::Pending => "pending",
+            FileState::Active => "active",
+            FileState::Done => "done",
+            FileState::Cached => "cached",
+            FileState::Failed => "failed",
+        }
+    }
+}
+
+/// Counters of a file being downloaded, updated by the download tasks and
+/// read by the reporter.
+#[derive(Debug)]
+pub(crate) struct FileProgress {
+    /// Bytes of the file on disk, including those of a resumed `.part` file.
+    received: AtomicU64,
+    /// Total size in bytes, `u64::MAX` if unknown.
+    total: AtomicU64,
+    /// Bytes received over the network, across all attempts.
+    transferred: AtomicU64,
+    state: AtomicU8,
+}
+
+impl Default for FileProgress {
+    fn default() -> Self {
+        Self {
+            received: AtomicU64::new(0),
+            total: AtomicU64::new(u64::MAX),
+            transferred: AtomicU64::new(0),
+            state: AtomicU8::new(FileState::Pending as u8),
+        }
+    }
+}
+
+impl FileProgress {
+    /// Start (or restart) the transfer at `offset` bytes, out of `total`.
+    pub(crate) fn start(&self, offset: u64, total: Option<u64>) {
+        self.received.store(offset, Ordering::Relaxed);
+        self.total
+            .store(total.unwrap_or(u64::MAX), Ordering::Relaxed);
+        self.state.store(FileState::Active as u8, Ordering::Relaxed);
+    }
+
+    pub(crate) fn advance(&self, bytes: u64) {
+        self.received.fetch_add(bytes, Ordering::Relaxed);
+        self.transferred.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Mark the file as finished; the bytes received are then its size.
+    pub(crate) fn finish(&self, success: bool) {
+        let state = if success {
+            let size = self.received.load(Ordering::Relaxed);
+            self.total.store(size, Ordering::Relaxed);
+            FileState::Done
+        } else {
+            FileState::Failed
+        };
+        self.state.store(state as u8, Ordering::Relaxed);
+    }
+
+    /// Mark the file as served from the cache. It counts no bytes, so that the
+    /// rate and ETA only reflect transfers.
+    pub(crate) fn cached(&self) {
+        self.received.store(0, Ordering::Relaxed);
+        self.total.store(0, Ordering::Relaxed);
+        self.state.store(FileState::Cached as u8, Ordering::Relaxed);
+    }
+
+    fn state(&self) -> FileState {
+        match self.state.load(Ordering::Relaxed) {
+            s if s == FileState::Active as u8 => FileState::Active,
+            s if s == FileState::Done as u8 => FileState::Done,
+            s if s == FileState::Cached as u8 => FileState::Cached,
+            s if s == FileState::Failed as u8 => FileState::Failed,
+            _ => FileState::Pending,
+        }
+    }
+}
+
+/// Progress of a single file at the time of a report.
+#[derive(Debug, Clone)]
+pub(crate) struct FileReport {
+    pub(crate) name: String,
+    pub(crate) received: u64,
+    pub(crate) total: Option<u64>,
+    pub(crate) state: FileState,
+}
+
+/// Aggregate progress of all files at the time of a report.
+#[derive(Debug, Clone)]
+pub(crate) struct ProgressReport {
+    pub(crate) files: Vec<FileReport>,
+    /// Bytes received per second, averaged over the recent reports.
+    pub(crate) rate: f64,
+    /// Whether all files are finished; the last report.
+    pub(crate) done: bool,
+}
+
+impl ProgressReport {
+    pub(crate) fn received(&self) -> u64 {
+        self.files.iter().map(|file| file.received).sum()
+    }
+
+    /// Total size of all files, known once every file has started.
+    pub(crate) fn total(&self) -> Option<u64> {
+        self.files.iter().map(|file| file.total).sum()
+    }
+
+    /// Estimated time left, if the total size is known and bytes are flowing.
+    pub(crate) fn eta(&self) -> Option<Duration> {
+        let left = self.total()?.saturating_sub(self.received());
+        (self.rate > 0.0).then(|| Duration::from_secs_f64(left as f64 / self.rate))
+    }
+
+    /// Number of files in `state`.
+    pub(crate) fn count(&self, state: FileState) -> usize {
+        self.files.iter().filter(|file| file.state == state).count()
+    }
+
+    /// One-line summary of all files then of the active ones, e.g.
+    /// `2/5 files | 1.2 GB / 4.5 GB | 12.3 MB/s | ETA 4m 30s | a.tar 45%`.
+    pub(crate) fn summary(&self) -> String {
+        let finished =
+            self.files.len() - self.count(FileState::Pending) - self.count(FileState::Active);
+        let mut out = format!(
+            "{}/{} files | {}",
+            finished,
+            self.files.len(),
+            format_bytes(self.received())
+        );
+        if let Some(total) = self.total() {
+            out.push_str(&format!(" / {}", format_bytes(total)));
+        }
+        out.push_str(&format!(" | {}/s", format_bytes(self.rate as u64)));
+        if let Some(eta) = self.eta().filter(|_| !self.done) {
+            out.push_str(&format!(" | ETA {}", format_duration(eta)));
+        }
+        for file in self.files.iter().filter(|f| f.state == FileState::Active) {
+            match file.total.filter(|total| *total > 0) {
+                Some(total) => out.push_str(&format!(
+                    " | {} {:.0}%",
+                    file.name,
+                    file.received as f64 / total as f64 * 100.0
+                )),
+                None => out.push_str(&format!(" | {} {}", file.name, format_bytes(file.received))),
+            }
+        }
+        out
+    }
+}
+
+/// Progress of all files of a download, shared between the download tasks and
+/// the reporter.
+pub(crate) struct Progress {
+    names: Vec<String>,
+    files: Vec<Arc<FileProgress>>,
+    /// Time and bytes transferred at the previous report, to compute the rate.
+    last: Option<(Instant, u64)>,
+    rate: f64,
+}
+
+impl Progress {
+    pub(crate) fn new<'a>(destfiles: impl Iterator<Item = &'a Path>) -> Self {
+        let names: Vec<String> = destfiles
+            .map(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            })
+            .collect();
+        let files = names.iter().map(|_| Arc::default()).collect();
+        Self {
+            names,
+            files,
+            last: None,
+            rate: 0.0,
+        }
+    }
+
+    /// Counters of the `i`-th file.
+    pub(crate) fn file(&self, i: usize) -> Arc<FileProgress> {
+        self.files[i].clone()
+    }
+
+    /// Take a report of the current progress, updating the transfer rate.
+    pub(crate) fn report(&mut self, done: bool) -> ProgressReport {
+        let files: Vec<FileReport> = self
+            .names
+            .iter()
+            .zip(self.files.iter())
+            .map(|(name, file)| FileReport {
+                name: name.clone(),
+                received: file.received.load(Ordering::Relaxed),
+                total: Some(file.total.load(Ordering::Relaxed)).filter(|t| *t != u64::MAX),
+                state: file.state(),
+            })
+            .collect();
+        let transferred = self
+            .files
+            .iter()
+            .map(|file| file.transferred.load(Ordering::Relaxed))
+            .sum();
+        let now = Instant::now();
+        if let Some((time, bytes)) = self.last {
+            let secs = now.duration_since(time).as_secs_f64();
+            if secs > 0.0 {
+                let rate = (transferred - bytes) as f64 / secs;
+                // Smooth out bursts of the network.
+                self.rate = if self.rate > 0.0 {
+                    0.3 * rate + 0.7 * self.rate
+                } else {
+                    rate
+                };
+            }
+        }
+        self.last = Some((now, transferred));
+        ProgressReport {
+            files,
+            rate: self.rate,
+            done,
+        }
+    }
+}
+
+/// Format a number of bytes with SI units, e.g. `1.2 GB`.
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["kB", "MB", "GB", "TB", "PB"];
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Format a duration with its two largest units, e.g. `4m 30s`.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, _) => format!("{}h {}m", h, m),
+    }
+}
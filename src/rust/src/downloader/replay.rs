@@ -0,0 +1,253 @@
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http_body::{Frame, SizeHint};
+use reqwest::header::{HeaderName, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, RANGE};
+use reqwest::{Body, Request, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::io::ReaderStream;
+
+use super::DownloadError;
+
+/// Whether network access goes to the servers or to recorded fixtures.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) enum ReplayMode {
+    /// Send requests to the servers.
+    #[default]
+    Live,
+    /// Send requests to the servers and save every response in a directory.
+    Record(PathBuf),
+    /// Serve responses only from a directory of recorded fixtures.
+    Replay(PathBuf),
+}
+
+fn global() -> &'static RwLock<Arc<ReplayMode>> {
+    static MODE: OnceLock<RwLock<Arc<ReplayMode>>> = OnceLock::new();
+    MODE.get_or_init(|| RwLock::new(Arc::new(ReplayMode::default())))
+}
+
+impl ReplayMode {
+    /// The mode currently used for network access.
+    pub(crate) fn current() -> Arc<ReplayMode> {
+        global().read().unwrap().clone()
+    }
+
+    /// Replace the mode used for network access.
+    pub(crate) fn set(mode: ReplayMode) {
+        let mut current = global().write().unwrap();
+        if **current != mode {
+            *current = Arc::new(mode);
+        }
+    }
+}
+
+/// A recorded response, saved as `<key>.json` next to its body `<key>.body`.
+#[derive(Serialize, Deserialize)]
+struct Fixture {
+    method: String,
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+}
+
+/// Send an HTTP request according to the current [`ReplayMode`].
+///
+/// When recording, the body is streamed into the fixture directory, and the
+/// returned response streams it back from there rather than from the server.
+/// When replaying, a request without fixture fails with
+/// [`DownloadError::ReplayMiss`] instead of reaching the network.
+pub(crate) async fn send(request: RequestBuilder) -> Result<Response, DownloadError> {
+    let mode = ReplayMode::current();
+    if *mode == ReplayMode::Live {
+        return Ok(request.send().await?);
+    }
+    let (client, request) = request.build_split();
+    let request = request?;
+    let key = fixture_key(&request);
+    match mode.as_ref() {
+        ReplayMode::Live => unreachable!(),
+        ReplayMode::Record(dir) => {
+            let method = request.method().to_string();
+            let url = request.url().to_string();
+            let response = client.execute(request).await?;
+            let fixture = Fixture {
+                method,
+                url,
+                status: response.status().as_u16(),
+                headers: response
+                    .headers()
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_string()))
+                    })
+                    .collect(),
+            };
+            save_fixture(dir, &key, &fixture, response).await?;
+            fixture_response(dir, &key, fixture).await
+        }
+        ReplayMode::Replay(dir) => {
+            let Some(fixture) = load_fixture(dir, &key).await? else {
+                return Err(DownloadError::ReplayMiss {
+                    method: request.method().to_string(),
+                    url: request.url().to_string(),
+                    dir: dir.to_string_lossy().into_owned(),
+                });
+            };
+            fixture_response(dir, &key, fixture).await
+        }
+    }
+}
+
+/// Fail if `url` would be accessed over FTP while recording or replaying,
+/// which only support HTTP.
+pub(crate) fn ensure_live(url: &str) -> Result<(), DownloadError> {
+    match ReplayMode::current().as_ref() {
+        ReplayMode::Live => Ok(()),
+        _ => Err(DownloadError::ReplayFtp(url.to_string())),
+    }
+}
+
+/// Parameters of a form body left out of the fixture key, as they identify
+/// the client rather than the query: fixtures recorded with an E-utilities
+/// API key must also be found without one.
+const UNKEYED_PARAMS: [&[u8]; 2] = [b"api_key", b"tool"];
+
+/// Identify a request by its method, URL, body and the headers changing the
+/// response, as the hex-encoded SHA-256 of them.
+fn fixture_key(request: &Request) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(request.method().as_str());
+    hasher.update(b" ");
+    hasher.update(request.url().as_str());
+    for name in [RANGE, IF_NONE_MATCH, IF_MODIFIED_SINCE] {
+        if let Some(value) = request.headers().get(&name) {
+            hasher.update(b"\n");
+            hasher.update(name.as_str());
+            hasher.update(b": ");
+            hasher.update(value.as_bytes());
+        }
+    }
+    if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+        hasher.update(b"\n\n");
+        let params = body.split(|b| *b == b'&').filter(|param| {
+            let name = param.split(|b| *b == b'=').next().unwrap_or_default();
+            !UNKEYED_PARAMS.contains(&name)
+        });
+        for (i, param) in params.enumerate() {
+            if i > 0 {
+                hasher.update(b"&");
+            }
+            hasher.update(param);
+        }
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Save `fixture`, streaming the body of `response` into `<key>.body`.
+async fn save_fixture(
+    dir: &Path,
+    key: &str,
+    fixture: &Fixture,
+    response: Response,
+) -> Result<(), DownloadError> {
+    fs::create_dir_all(dir).await?;
+    // Write the body first, so a fixture is never found without its body.
+    let tmp = dir.join(format!("{}.body.tmp", key));
+    if let Err(e) = write_body(&tmp, response).await {
+        let _ = fs::remove_file(&tmp).await;
+        return Err(e);
+    }
+    fs::rename(&tmp, dir.join(format!("{}.body", key))).await?;
+    let json = serde_json::to_vec_pretty(fixture)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let tmp = dir.join(format!("{}.json.tmp", key));
+    fs::write(&tmp, json).await?;
+    fs::rename(&tmp, dir.join(format!("{}.json", key))).await?;
+    Ok(())
+}
+
+async fn write_body(path: &Path, response: Response) -> Result<(), DownloadError> {
+    let mut file = File::create(path).await?;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        file.write_all(&chunk?).await?;
+    }
+    file.flush().await?;
+    Ok(())
+}
+
+async fn load_fixture(dir: &Path, key: &str) -> Result<Option<Fixture>, DownloadError> {
+    let json = match fs::read(dir.join(format!("{}.json", key))).await {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let fixture: Fixture = serde_json::from_slice(&json)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    Ok(Some(fixture))
+}
+
+/// Build the response of `fixture`, streaming its body from `<key>.body`.
+async fn fixture_response(
+    dir: &Path,
+    key: &str,
+    fixture: Fixture,
+) -> Result<Response, DownloadError> {
+    let file = File::open(dir.join(format!("{}.body", key))).await?;
+    let len = file.metadata().await?.len();
+    let mut response = http::Response::new(Body::wrap(FixtureBody {
+        stream: ReaderStream::new(file),
+        len,
+    }));
+    *response.status_mut() =
+        http::StatusCode::from_u16(fixture.status).map_err(|_| DownloadError::Status {
+            status: fixture.status,
+            retry_after: None,
+        })?;
+    let headers = response.headers_mut();
+    for (name, value) in fixture.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    Ok(Response::from(response))
+}
+
+/// The body of a fixture, streamed from its file with a known length, so that
+/// [`Response::content_length`] is set as for a response of the server.
+struct FixtureBody {
+    stream: ReaderStream<File>,
+    len: u64,
+}
+
+impl http_body::Body for FixtureBody {
+    type Data = Bytes;
+    type Error = std::io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, std::io::Error>>> {
+        Pin::new(&mut self.stream)
+            .poll_next(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.len)
+    }
+}
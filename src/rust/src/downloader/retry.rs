@@ -0,0 +1,153 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Result};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+use super::DownloadError;
+
+/// Kinds of transport errors that may be retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RetryErrorKind {
+    /// Failed to establish a connection (DNS, refused, TLS handshake).
+    Connect,
+    /// The request or transfer timed out.
+    Timeout,
+    /// The connection broke while sending the request or reading the body.
+    Transfer,
+}
+
+impl fmt::Display for RetryErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            RetryErrorKind::Connect => "connect",
+            RetryErrorKind::Timeout => "timeout",
+            RetryErrorKind::Transfer => "transfer",
+        })
+    }
+}
+
+impl FromStr for RetryErrorKind {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "connect" => Ok(RetryErrorKind::Connect),
+            "timeout" => Ok(RetryErrorKind::Timeout),
+            "transfer" => Ok(RetryErrorKind::Transfer),
+            _ => Err(anyhow!(
+                "Expected one of 'connect', 'timeout', or 'transfer', got '{}'",
+                s
+            )),
+        }
+    }
+}
+
+/// When and how long to wait before downloading a file again.
+///
+/// The delay before attempt `n + 1` is `base_delay * 2^(n - 1)`, capped at
+/// `max_delay` and randomly scaled by up to `±jitter`. A `Retry-After` header
+/// sent by the server takes precedence when it asks for a longer wait.
+#[derive(Debug, Clone)]
+pub(crate) struct RetryPolicy {
+    pub(crate) max_attempts: u32,
+    pub(crate) base_delay: Duration,
+    pub(crate) max_delay: Duration,
+    pub(crate) jitter: f64,
+    pub(crate) status: Vec<u16>,
+    pub(crate) errors: Vec<RetryErrorKind>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            jitter: 0.25,
+            // Request Timeout, Too Many Requests, and transient server errors.
+            status: vec![408, 429, 500, 502, 503, 504],
+            errors: vec![
+                RetryErrorKind::Connect,
+                RetryErrorKind::Timeout,
+                RetryErrorKind::Transfer,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether `error` is worth another attempt under this policy.
+    pub(crate) fn is_retryable(&self, error: &DownloadError) -> bool {
+        match error {
+            DownloadError::Status { status, .. } => self.status.contains(status),
+            _ => error_kind(error).is_some_and(|kind| self.errors.contains(&kind)),
+        }
+    }
+
+    /// Delay before the attempt following the `attempt`-th (1-based) failure.
+    pub(crate) fn delay(&self, attempt: u32, error: &DownloadError) -> Duration {
+        let exp = attempt.saturating_sub(1).min(31);
+        let backoff = self.base_delay.saturating_mul(1 << exp).min(self.max_delay);
+        let backoff = if self.jitter > 0.0 {
+            // Uniform factor in [1 - jitter, 1 + jitter].
+            let factor = 1.0 + self.jitter * (2.0 * random_unit() - 1.0);
+            backoff.mul_f64(factor.max(0.0))
+        } else {
+            backoff
+        };
+        match error {
+            DownloadError::Status {
+                retry_after: Some(after),
+                ..
+            } => backoff.max(*after),
+            _ => backoff,
+        }
+    }
+}
+
+/// Classify transport errors; HTTP status and local I/O errors have no kind.
+fn error_kind(error: &DownloadError) -> Option<RetryErrorKind> {
+    match error {
+        DownloadError::Connect(_) => Some(RetryErrorKind::Connect),
+        DownloadError::Timeout => Some(RetryErrorKind::Timeout),
+        DownloadError::Transport(_) => Some(RetryErrorKind::Transfer),
+        // 4xx FTP replies are transient negative completions (RFC 959).
+        DownloadError::Ftp { status, .. } if (400 .. 500).contains(status) => {
+            Some(RetryErrorKind::Transfer)
+        }
+        DownloadError::Request(e) if e.is_timeout() => Some(RetryErrorKind::Timeout),
+        DownloadError::Request(e) if e.is_connect() => Some(RetryErrorKind::Connect),
+        DownloadError::Request(e) if e.is_body() || e.is_decode() || e.is_request() => {
+            Some(RetryErrorKind::Transfer)
+        }
+        _ => None,
+    }
+}
+
+/// Parse `Retry-After`, given either as delay-seconds or as an HTTP-date.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// A pseudo-random number in `[0, 1)`, good enough to spread out retries.
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use anyhow::Result;
+use reqwest::header::{CONTENT_LENGTH, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, StatusCode};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use super::http::validators;
+use super::replay::send;
+use super::retry::retry_after;
+use super::{new_client, runtime, DownloadError};
+use crate::cache::{now, CacheEntry};
+
+/// Maximal number of local files checked with the server at a time.
+const CHECK_CONCURRENCY: usize = 5;
+
+/// When to check with the server that a cached file is still up to date.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum RevalidatePolicy {
+    /// Always serve cached files as is.
+    #[default]
+    Never,
+    /// Check files fetched (or last checked) longer ago than this.
+    OlderThan(Duration),
+    /// Check every cached file.
+    Always,
+}
+
+impl RevalidatePolicy {
+    /// Whether `entry` must be checked before being served.
+    pub(crate) fn is_due(&self, entry: &CacheEntry) -> bool {
+        self.is_due_since(entry.fetched)
+    }
+
+    /// Whether a file fetched (or last checked) at `fetched`, in seconds since
+    /// the Unix epoch, must be checked.
+    fn is_due_since(&self, fetched: u64) -> bool {
+        match self {
+            RevalidatePolicy::Never => false,
+            RevalidatePolicy::OlderThan(age) => now().saturating_sub(fetched) > age.as_secs(),
+            RevalidatePolicy::Always => true,
+        }
+    }
+}
+
+/// Ask the server whether the cached `entry` is outdated.
+///
+/// A `HEAD` request is sent with the stored validators as `If-None-Match` and
+/// `If-Modified-Since`, so an up-to-date file is answered with `304 Not
+/// Modified`. Servers ignoring conditional requests answer `200 OK`, in which
+/// case the returned validators are compared with the stored ones, or the
+/// `Content-Length` with the size if no validator was stored. FTP URLs have no
+/// validators and are never reported outdated.
+pub(crate) async fn is_outdated(
+    client: &Client,
+    entry: &CacheEntry,
+) -> Result<bool, DownloadError> {
+    if entry.url.starts_with("ftp://") {
+        return Ok(false);
+    }
+    let mut request = client.head(&entry.url);
+    if let Some(etag) = &entry.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &entry.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    let response = send(request).await?;
+    match response.status() {
+        StatusCode::NOT_MODIFIED => Ok(false),
+        s if s.is_success() => {
+            let current = validators(response.headers());
+            Ok(match (&entry.etag, &entry.last_modified) {
+                (Some(etag), _) if current.etag.is_some() => current.etag.as_ref() != Some(etag),
+                (_, Some(modified)) if current.last_modified.is_some() => {
+                    current.last_modified.as_ref() != Some(modified)
+                }
+                _ => {
+                    let size = response
+                        .headers()
+                        .get(CONTENT_LENGTH)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok());
+                    size != Some(entry.size)
+                }
+            })
+        }
+        s => Err(DownloadError::Status {
+            status: s.as_u16(),
+            retry_after: retry_after(response.headers()),
+        }),
+    }
+}
+
+/// Check which of `files`, local files downloaded before from the paired URL,
+/// are outdated, for those due for a check under `revalidate` by their
+/// modification time. Failing to reach the server keeps the local file.
+pub(crate) fn outdated_files(
+    files: Vec<(String, PathBuf)>,
+    revalidate: RevalidatePolicy,
+) -> Result<Vec<bool>> {
+    let client = new_client()?;
+    let semaphore = Arc::new(Semaphore::new(CHECK_CONCURRENCY));
+    Ok(runtime()?.block_on(async move {
+        let mut set = JoinSet::new();
+        let n = files.len();
+        for (i, (url, file)) in files.into_iter().enumerate() {
+            let client = client.clone();
+            let semaphore = semaphore.clone();
+            set.spawn(async move {
+                let Some(modified) = modified(&file) else {
+                    return (i, false);
+                };
+                if !revalidate.is_due_since(modified) {
+                    return (i, false);
+                }
+                // The semaphore is never closed, so acquiring cannot fail.
+                let _permit = semaphore.acquire_owned().await.unwrap();
+                match is_file_outdated(&client, &url, &file).await {
+                    Ok(outdated) => (i, outdated),
+                    Err(e) => {
+                        eprintln!(
+                            "Warning: Failed to check whether {} is up to date, keeping {}: {}",
+                            url,
+                            file.display(),
+                            e
+                        );
+                        (i, false)
+                    }
+                }
+            });
+        }
+        let mut out = vec![false; n];
+        while let Some(joined) = set.join_next().await {
+            // Tasks never panic outside of bugs; surface those loudly.
+            let (i, outdated) = joined.expect("revalidation task panicked");
+            out[i] = outdated;
+        }
+        out
+    }))
+}
+
+/// Ask the server whether the local `file` downloaded from `url` is outdated.
+///
+/// Local files carry no validators, so a `HEAD` request is sent with their
+/// modification time as `If-Modified-Since`. Servers ignoring conditional
+/// requests answer `200 OK`, in which case their `Last-Modified` is compared
+/// with the modification time, or the `Content-Length` with the size if there
+/// is none. FTP URLs are never reported outdated.
+async fn is_file_outdated(client: &Client, url: &str, file: &Path) -> Result<bool, DownloadError> {
+    if url.starts_with("ftp://") {
+        return Ok(false);
+    }
+    let metadata = tokio::fs::metadata(file).await?;
+    let modified = metadata.modified()?;
+    let response = send(
+        client
+            .head(url)
+            .header(IF_MODIFIED_SINCE, httpdate::fmt_http_date(modified)),
+    )
+    .await?;
+    match response.status() {
+        StatusCode::NOT_MODIFIED => Ok(false),
+        s if s.is_success() => {
+            let headers = response.headers();
+            let last_modified = headers
+                .get(LAST_MODIFIED)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| httpdate::parse_http_date(v).ok());
+            Ok(match last_modified {
+                Some(last_modified) => last_modified > modified,
+                None => {
+                    let size = headers
+                        .get(CONTENT_LENGTH)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok());
+                    size != Some(metadata.len())
+                }
+            })
+        }
+        s => Err(DownloadError::Status {
+            status: s.as_u16(),
+            retry_after: retry_after(response.headers()),
+        }),
+    }
+}
+
+/// Modification time of `file` in seconds since the Unix epoch, `None` if it
+/// does not exist.
+fn modified(file: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(file).ok()?.modified().ok()?;
+    Some(
+        modified
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    )
+}
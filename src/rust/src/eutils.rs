@@ -0,0 +1,320 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tokio::time::{sleep_until, Instant};
+
+use crate::downloader::{new_client, retry_after, send, DownloadError, RetryPolicy};
+
+mod gds;
+mod link;
+mod sra;
+
+pub(crate) use gds::{search_gds, GDSSummary};
+pub(crate) use link::link_geo;
+pub(crate) use sra::{sra_runs, SRARun};
+
+const EUTILS_URL: &str = "https://eutils.ncbi.nlm.nih.gov/entrez/eutils";
+
+/// Environment variable holding the NCBI API key, the same as rentrez.
+const API_KEY_ENV: &str = "ENTREZ_KEY";
+
+/// Client of the NCBI E-utilities, always asking for JSON replies.
+///
+/// NCBI allows 3 requests per second, or 10 with an API key; requests are
+/// spaced out accordingly, even when sent from concurrent tasks. Rejected
+/// requests (e.g. `429 Too Many Requests`) are retried with backoff.
+pub(crate) struct EUtils {
+    client: Client,
+    api_key: Option<String>,
+    interval: Duration,
+    /// Earliest time the next request may be sent.
+    next: Mutex<Instant>,
+    retry: RetryPolicy,
+}
+
+/// Matches of an esearch kept on the Entrez history server.
+pub(crate) struct SearchHistory {
+    pub(crate) count: usize,
+    pub(crate) webenv: String,
+    pub(crate) query_key: String,
+}
+
+#[derive(Deserialize)]
+struct ESearchReply {
+    esearchresult: ESearchResult,
+}
+
+#[derive(Deserialize)]
+struct ESearchResult {
+    #[serde(default)]
+    count: Option<String>,
+    #[serde(default)]
+    webenv: Option<String>,
+    #[serde(default)]
+    querykey: Option<String>,
+    #[serde(default)]
+    idlist: Vec<String>,
+    #[serde(default, rename = "ERROR")]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ELinkReply {
+    #[serde(default)]
+    linksets: Vec<LinkSet>,
+    #[serde(default, rename = "ERROR")]
+    error: Option<String>,
+}
+
+/// Links of the UIDs `ids` of `dbfrom`.
+#[derive(Deserialize)]
+pub(crate) struct LinkSet {
+    #[serde(default)]
+    pub(crate) ids: Vec<String>,
+    #[serde(default)]
+    pub(crate) linksetdbs: Vec<LinkSetDb>,
+}
+
+/// Links to one database, by one link name (e.g. `gds_pubmed`).
+#[derive(Deserialize)]
+pub(crate) struct LinkSetDb {
+    pub(crate) dbto: String,
+    pub(crate) linkname: String,
+    #[serde(default)]
+    pub(crate) links: Vec<String>,
+}
+
+impl EUtils {
+    /// Create a client using `api_key`, or the `ENTREZ_KEY` environment
+    /// variable if `None`.
+    pub(crate) fn new(api_key: Option<String>) -> Result<Self> {
+        let api_key = api_key
+            .or_else(|| std::env::var(API_KEY_ENV).ok())
+            .filter(|key| !key.is_empty());
+        let rate = if api_key.is_some() { 10 } else { 3 };
+        Ok(Self {
+            client: new_client()?,
+            api_key,
+            interval: Duration::from_secs(1) / rate,
+            next: Mutex::new(Instant::now()),
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Run esearch on `db` with `usehistory=y`, keeping the matched UIDs on
+    /// the history server for later paging.
+    pub(crate) async fn esearch(&self, db: &str, term: &str) -> Result<SearchHistory> {
+        let reply: ESearchReply = self
+            .call("esearch.fcgi", &[
+                ("db", db),
+                ("term", term),
+                ("usehistory", "y"),
+                ("retmax", "0"),
+            ])
+            .await?;
+        let result = reply.esearchresult;
+        if let Some(error) = result.error {
+            return Err(anyhow!("esearch failed: {}", error));
+        }
+        let count = result
+            .count
+            .as_deref()
+            .and_then(|count| count.parse().ok())
+            .ok_or_else(|| anyhow!("esearch reply without a valid count"))?;
+        Ok(SearchHistory {
+            count,
+            webenv: result.webenv.unwrap_or_default(),
+            query_key: result.querykey.unwrap_or_default(),
+        })
+    }
+
+    /// Run esearch on `db`, returning up to `retmax` matched UIDs.
+    pub(crate) async fn esearch_ids(
+        &self,
+        db: &str,
+        term: &str,
+        retmax: usize,
+    ) -> Result<Vec<String>> {
+        let retmax = retmax.to_string();
+        let reply: ESearchReply = self
+            .call("esearch.fcgi", &[
+                ("db", db),
+                ("term", term),
+                ("retmax", &retmax),
+            ])
+            .await?;
+        let result = reply.esearchresult;
+        if let Some(error) = result.error {
+            return Err(anyhow!("esearch failed: {}", error));
+        }
+        Ok(result.idlist)
+    }
+
+    /// Fetch the document summaries of `retmax` records of `history` starting
+    /// at `retstart` (0-based), in the order of the search.
+    pub(crate) async fn esummary<T: DeserializeOwned>(
+        &self,
+        db: &str,
+        history: &SearchHistory,
+        retstart: usize,
+        retmax: usize,
+    ) -> Result<Vec<T>> {
+        let retstart = retstart.to_string();
+        let retmax = retmax.to_string();
+        let reply: Value = self
+            .call("esummary.fcgi", &[
+                ("db", db),
+                ("WebEnv", &history.webenv),
+                ("query_key", &history.query_key),
+                ("retstart", &retstart),
+                ("retmax", &retmax),
+                ("version", "2.0"),
+            ])
+            .await?;
+        summaries_from_reply(reply)
+    }
+
+    /// Fetch the document summaries of the records `ids`.
+    pub(crate) async fn esummary_ids<T: DeserializeOwned>(
+        &self,
+        db: &str,
+        ids: &[String],
+    ) -> Result<Vec<T>> {
+        let ids = ids.join(",");
+        let reply: Value = self
+            .call("esummary.fcgi", &[
+                ("db", db),
+                ("id", &ids),
+                ("version", "2.0"),
+            ])
+            .await?;
+        summaries_from_reply(reply)
+    }
+
+    /// Run elink from `dbfrom` to `db`, giving one link set per UID of `ids`.
+    pub(crate) async fn elink(
+        &self,
+        dbfrom: &str,
+        db: &str,
+        ids: &[String],
+    ) -> Result<Vec<LinkSet>> {
+        let mut params = vec![("dbfrom", dbfrom), ("db", db)];
+        // Repeated `id` parameters keep the links of every UID apart.
+        params.extend(ids.iter().map(|id| ("id", id.as_str())));
+        let reply: ELinkReply = self.call("elink.fcgi", &params).await?;
+        if let Some(error) = reply.error {
+            return Err(anyhow!("elink failed: {}", error));
+        }
+        Ok(reply.linksets)
+    }
+
+    /// Fetch the records `ids` of `db` as plain text in the format `rettype`,
+    /// e.g. the run information CSV of SRA experiments (`runinfo`).
+    pub(crate) async fn efetch_text(
+        &self,
+        db: &str,
+        ids: &[String],
+        rettype: &str,
+    ) -> Result<String> {
+        let ids = ids.join(",");
+        let bytes = self
+            .fetch("efetch.fcgi", &[
+                ("db", db),
+                ("id", &ids),
+                ("rettype", rettype),
+                ("retmode", "text"),
+            ])
+            .await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Query `utility` (e.g. `esearch.fcgi`) with `params` and decode the
+    /// JSON reply.
+    async fn call<T: DeserializeOwned>(&self, utility: &str, params: &[(&str, &str)]) -> Result<T> {
+        let mut params = params.to_vec();
+        params.push(("retmode", "json"));
+        let bytes = self.fetch(utility, &params).await?;
+        serde_json::from_slice(&bytes).with_context(|| format!("Invalid reply of {}", utility))
+    }
+
+    /// Query `utility` with `params`, returning the raw reply.
+    ///
+    /// Parameters are sent in a POST body, so long lists of UIDs do not hit
+    /// URL length limits.
+    async fn fetch(&self, utility: &str, params: &[(&str, &str)]) -> Result<Vec<u8>> {
+        let url = format!("{}/{}", EUTILS_URL, utility);
+        let mut form = Url::parse(EUTILS_URL).with_context(|| "Invalid E-utilities URL")?;
+        form.query_pairs_mut()
+            .extend_pairs(params)
+            .append_pair("tool", "geokit");
+        if let Some(key) = self.api_key.as_deref() {
+            form.query_pairs_mut().append_pair("api_key", key);
+        }
+        let body = form.query().unwrap_or_default().to_string();
+        let mut attempt = 1;
+        loop {
+            self.throttle().await;
+            match self.send(&url, &body).await {
+                Ok(bytes) => return Ok(bytes),
+                Err(e) if attempt < self.retry.max_attempts && self.retry.is_retryable(&e) => {
+                    tokio::time::sleep(self.retry.delay(attempt, &e)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e).with_context(|| format!("Failed to query {}", utility)),
+            }
+        }
+    }
+
+    async fn send(&self, url: &str, body: &str) -> Result<Vec<u8>, DownloadError> {
+        let response = send(
+            self.client
+                .post(url)
+                .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(body.to_string()),
+        )
+        .await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DownloadError::Status {
+                status: status.as_u16(),
+                retry_after: retry_after(response.headers()),
+            });
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Wait until the next request is allowed by the rate limit.
+    async fn throttle(&self) {
+        let mut next = self.next.lock().await;
+        sleep_until(*next).await;
+        *next = Instant::now() + self.interval;
+    }
+}
+
+/// Decode the records of an esummary reply, in the order of its `uids`.
+fn summaries_from_reply<T: DeserializeOwned>(reply: Value) -> Result<Vec<T>> {
+    if let Some(error) = reply.get("error").and_then(Value::as_str) {
+        return Err(anyhow!("esummary failed: {}", error));
+    }
+    let result = reply
+        .get("result")
+        .ok_or_else(|| anyhow!("esummary reply without a result"))?;
+    let uids = result
+        .get("uids")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("esummary reply without uids"))?;
+    uids.iter()
+        .filter_map(Value::as_str)
+        .map(|uid| {
+            let record = result.get(uid).cloned().unwrap_or(Value::Null);
+            serde_json::from_value(record)
+                .with_context(|| format!("Invalid summary of UID {}", uid))
+        })
+        .collect()
+}
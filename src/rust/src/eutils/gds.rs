@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::EUtils;
+use crate::downloader::runtime;
+
+/// Document summary of a record in the Entrez `gds` database, which holds GEO
+/// DataSets, Series, Platforms and Samples.
+pub(crate) struct GDSSummary {
+    pub(crate) uid: String,
+    pub(crate) accession: String,
+    pub(crate) title: String,
+    pub(crate) summary: String,
+    pub(crate) taxon: String,
+    /// `GDS`, `GSE`, `GPL` or `GSM`.
+    pub(crate) entry_type: String,
+    /// Experiment type, e.g. `Expression profiling by array`.
+    pub(crate) gds_type: String,
+    /// Linked Platform accessions.
+    pub(crate) gpl: Vec<String>,
+    /// Linked Series accessions.
+    pub(crate) gse: Vec<String>,
+    /// Linked Sample accessions.
+    pub(crate) gsm: Vec<String>,
+    pub(crate) n_samples: Option<u64>,
+    /// Public release date (`YYYY/MM/DD`).
+    pub(crate) pdat: String,
+}
+
+/// A summary as returned by esummary, with most fields as plain strings.
+#[derive(Deserialize)]
+struct Record {
+    #[serde(default)]
+    uid: String,
+    #[serde(default)]
+    accession: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    taxon: String,
+    #[serde(default)]
+    entrytype: String,
+    #[serde(default)]
+    gdstype: String,
+    /// Semicolon-separated numbers without the `GPL` prefix, e.g. `96;570`.
+    #[serde(default)]
+    gpl: String,
+    /// Semicolon-separated numbers without the `GSE` prefix.
+    #[serde(default)]
+    gse: String,
+    #[serde(default)]
+    samples: Vec<Sample>,
+    #[serde(default)]
+    n_samples: Option<u64>,
+    #[serde(default)]
+    pdat: String,
+}
+
+#[derive(Deserialize)]
+struct Sample {
+    #[serde(default)]
+    accession: String,
+}
+
+impl From<Record> for GDSSummary {
+    fn from(record: Record) -> Self {
+        Self {
+            uid: record.uid,
+            accession: record.accession,
+            title: record.title,
+            summary: record.summary,
+            taxon: record.taxon,
+            entry_type: record.entrytype,
+            gds_type: record.gdstype,
+            gpl: prefix_ids("GPL", &record.gpl),
+            gse: prefix_ids("GSE", &record.gse),
+            gsm: record
+                .samples
+                .into_iter()
+                .map(|sample| sample.accession)
+                .filter(|acc| !acc.is_empty())
+                .collect(),
+            n_samples: record.n_samples,
+            pdat: record.pdat,
+        }
+    }
+}
+
+/// Search the `gds` database with `query`, fetching the summaries of all
+/// matched records `step` at a time.
+pub(crate) fn search_gds(
+    query: &str,
+    step: usize,
+    api_key: Option<String>,
+) -> Result<Vec<GDSSummary>> {
+    let eutils = EUtils::new(api_key)?;
+    let step = step.max(1);
+    runtime()?.block_on(async move {
+        let history = eutils
+            .esearch("gds", query)
+            .await
+            .with_context(|| format!("Failed to search GEO for '{}'", query))?;
+        let mut out = Vec::with_capacity(history.count);
+        for retstart in (0 .. history.count).step_by(step) {
+            let records: Vec<Record> = eutils
+                .esummary("gds", &history, retstart, step)
+                .await
+                .with_context(|| format!("Failed to fetch records from {}", retstart + 1))?;
+            out.extend(records.into_iter().map(GDSSummary::from));
+        }
+        Ok(out)
+    })
+}
+
+/// Turn `96;570` into `GPL96`, `GPL570`.
+fn prefix_ids(prefix: &str, ids: &str) -> Vec<String> {
+    ids.split(';')
+        .map(|id| id.trim())
+        .filter(|id| !id.is_empty())
+        .map(|id| format!("{}{}", prefix, id))
+        .collect()
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::EUtils;
+use crate::downloader::runtime;
+
+/// Number of accessions resolved and linked per request.
+const BATCH_SIZE: usize = 200;
+
+/// A link from a GEO accession to a record of another Entrez database.
+pub(crate) struct GEOLink {
+    pub(crate) accession: String,
+    /// Entrez link name, e.g. `gds_pubmed`.
+    pub(crate) link_name: String,
+    pub(crate) target_db: String,
+    pub(crate) target_id: String,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    #[serde(default)]
+    uid: String,
+    #[serde(default)]
+    accession: String,
+}
+
+/// Link GEO `accessions` to the records of each of `target_dbs` (e.g.
+/// `pubmed`, `sra`, `bioproject` or `taxonomy`) with elink.
+///
+/// Accessions are first resolved to UIDs of the Entrez `gds` database, then
+/// linked [`BATCH_SIZE`] at a time. Returns the links in the order of
+/// `accessions`, and the accessions without any `gds` record.
+pub(crate) fn link_geo(
+    accessions: &[String],
+    target_dbs: &[String],
+    api_key: Option<String>,
+) -> Result<(Vec<GEOLink>, Vec<String>)> {
+    let eutils = EUtils::new(api_key)?;
+    runtime()?.block_on(async move {
+        let mut links = Vec::new();
+        let mut unresolved = Vec::new();
+        for batch in accessions.chunks(BATCH_SIZE) {
+            let uids = resolve_uids(&eutils, batch).await?;
+            let ids: Vec<String> = uids.values().cloned().collect();
+            // (link name, target db, target id) of every linked UID
+            let mut by_uid: HashMap<String, Vec<(String, String, String)>> = HashMap::new();
+            for db in target_dbs.iter().filter(|_| !ids.is_empty()) {
+                let linksets = eutils
+                    .elink("gds", db, &ids)
+                    .await
+                    .with_context(|| format!("Failed to link GEO records to {}", db))?;
+                for linkset in linksets {
+                    for uid in linkset.ids.iter() {
+                        let out = by_uid.entry(uid.clone()).or_default();
+                        for linksetdb in linkset.linksetdbs.iter() {
+                            out.extend(linksetdb.links.iter().map(|id| {
+                                (
+                                    linksetdb.linkname.clone(),
+                                    linksetdb.dbto.clone(),
+                                    id.clone(),
+                                )
+                            }));
+                        }
+                    }
+                }
+            }
+            for accession in batch {
+                let Some(uid) = uids.get(&accession.to_uppercase()) else {
+                    unresolved.push(accession.clone());
+                    continue;
+                };
+                let found = by_uid.get(uid).into_iter().flatten();
+                links.extend(found.map(|(link_name, target_db, target_id)| GEOLink {
+                    accession: accession.clone(),
+                    link_name: link_name.clone(),
+                    target_db: target_db.clone(),
+                    target_id: target_id.clone(),
+                }));
+            }
+        }
+        Ok((links, unresolved))
+    })
+}
+
+/// Map the upper-cased `accessions` to their `gds` UIDs.
+///
+/// Searching the accession field may also match related records, e.g. the
+/// DataSets of a Series, so the summaries are checked for the exact accession.
+pub(super) async fn resolve_uids(
+    eutils: &EUtils,
+    accessions: &[String],
+) -> Result<HashMap<String, String>> {
+    let term = accessions
+        .iter()
+        .map(|acc| format!("{}[ACCN]", acc))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let ids = eutils
+        .esearch_ids("gds", &term, accessions.len() * 10)
+        .await
+        .with_context(|| "Failed to resolve GEO accessions to UIDs")?;
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let records: Vec<Record> = eutils
+        .esummary_ids("gds", &ids)
+        .await
+        .with_context(|| "Failed to resolve GEO accessions to UIDs")?;
+    let wanted: Vec<String> = accessions.iter().map(|acc| acc.to_uppercase()).collect();
+    Ok(records
+        .into_iter()
+        .map(|record| (record.accession.to_uppercase(), record.uid))
+        .filter(|(acc, _)| wanted.contains(acc))
+        .collect())
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+
+use super::link::resolve_uids;
+use super::EUtils;
+use crate::downloader::runtime;
+use crate::parser::runinfo::{parse_runinfo, RunInfo};
+
+/// Number of GEO accessions resolved and linked per request.
+const BATCH_SIZE: usize = 200;
+
+/// Number of SRA experiments summarised or fetched per request.
+const SRA_BATCH_SIZE: usize = 500;
+
+const ENA_FASTQ_URL: &str = "https://ftp.sra.ebi.ac.uk/vol1/fastq";
+
+/// A sequencing run of a GEO Series or Sample.
+pub(crate) struct SRARun {
+    /// The GEO accession the run was found for.
+    pub(crate) accession: String,
+    /// GSM accession of the sample, if the run belongs to a GEO sample.
+    pub(crate) gsm: Option<String>,
+    pub(crate) info: RunInfo,
+    /// fastq files of the run on the ENA FTP site (over HTTPS).
+    pub(crate) fastq: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct SRASummary {
+    #[serde(default)]
+    uid: String,
+    /// XML fragment describing the experiment.
+    #[serde(default)]
+    expxml: String,
+}
+
+/// Find the SRA runs of GEO `accessions` (GSE or GSM).
+///
+/// Accessions are resolved to `gds` UIDs and linked to SRA experiments with
+/// elink, whose runs are then read from the run information table of efetch.
+/// Returns the runs in the order of `accessions`, and the accessions without
+/// any `gds` record.
+pub(crate) fn sra_runs(
+    accessions: &[String],
+    api_key: Option<String>,
+) -> Result<(Vec<SRARun>, Vec<String>)> {
+    let eutils = EUtils::new(api_key)?;
+    runtime()?.block_on(async move {
+        let mut out = Vec::new();
+        let mut unresolved = Vec::new();
+        for batch in accessions.chunks(BATCH_SIZE) {
+            let uids = resolve_uids(&eutils, batch).await?;
+            let ids: Vec<String> = uids.values().cloned().collect();
+            // SRA experiment UIDs of every `gds` UID
+            let mut experiments: HashMap<String, Vec<String>> = HashMap::new();
+            if !ids.is_empty() {
+                let linksets = eutils
+                    .elink("gds", "sra", &ids)
+                    .await
+                    .with_context(|| "Failed to link GEO records to SRA")?;
+                for linkset in linksets {
+                    let links: Vec<String> = linkset
+                        .linksetdbs
+                        .iter()
+                        .flat_map(|db| db.links.iter().cloned())
+                        .collect();
+                    for uid in linkset.ids {
+                        experiments
+                            .entry(uid)
+                            .or_default()
+                            .extend(links.iter().cloned());
+                    }
+                }
+            }
+            let mut sra_ids: Vec<String> = experiments.values().flatten().cloned().collect();
+            sra_ids.sort_unstable();
+            sra_ids.dedup();
+            let runs = fetch_runs(&eutils, &sra_ids).await?;
+
+            for accession in batch {
+                let Some(uid) = uids.get(&accession.to_uppercase()) else {
+                    unresolved.push(accession.clone());
+                    continue;
+                };
+                let found = experiments
+                    .get(uid)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|sra_uid| runs.get(sra_uid))
+                    .flatten();
+                out.extend(found.map(|info| SRARun {
+                    accession: accession.clone(),
+                    gsm: Some(info.sample_name.clone()).filter(|name| name.starts_with("GSM")),
+                    fastq: ena_fastq(&info.run, &info.layout),
+                    info: info.clone(),
+                }));
+            }
+        }
+        Ok((out, unresolved))
+    })
+}
+
+/// Fetch the runs of the SRA experiments `ids`, grouped by experiment UID.
+async fn fetch_runs(eutils: &EUtils, ids: &[String]) -> Result<HashMap<String, Vec<RunInfo>>> {
+    let mut out: HashMap<String, Vec<RunInfo>> = HashMap::new();
+    for chunk in ids.chunks(SRA_BATCH_SIZE) {
+        // The run information table only holds experiment accessions.
+        let summaries: Vec<SRASummary> = eutils
+            .esummary_ids("sra", chunk)
+            .await
+            .with_context(|| "Failed to fetch SRA experiments")?;
+        let uids: HashMap<String, String> = summaries
+            .into_iter()
+            .filter_map(|summary| {
+                experiment_accession(&summary.expxml).map(|acc| (acc.to_string(), summary.uid))
+            })
+            .collect();
+        let text = eutils
+            .efetch_text("sra", chunk, "runinfo")
+            .await
+            .with_context(|| "Failed to fetch SRA run information")?;
+        let runs = parse_runinfo(&text).with_context(|| "Failed to parse SRA run information")?;
+        for run in runs {
+            if let Some(uid) = uids.get(&run.experiment) {
+                out.entry(uid.clone()).or_default().push(run);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Parse the accession from `<Experiment acc="SRX000001" ...>`.
+fn experiment_accession(expxml: &str) -> Option<&str> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r#"<Experiment\s[^>]*acc="([^"]+)""#)
+            .with_context(|| "Failed to create regex")
+            .unwrap()
+    })
+    .captures(expxml)
+    .and_then(|caps| caps.get(1))
+    .map(|m| m.as_str())
+}
+
+/// URLs of the fastq files of `run` mirrored by the ENA, e.g.
+/// `vol1/fastq/SRR123/004/SRR1234564/SRR1234564_1.fastq.gz`.
+///
+/// Runs with more than 6 digits are put in a sub-directory named after the
+/// extra digits, zero-padded to 3 characters.
+fn ena_fastq(run: &str, layout: &str) -> Vec<String> {
+    if run.len() < 9 || !run.is_ascii() {
+        return Vec::new();
+    }
+    let mut dir = format!("{}/{}", ENA_FASTQ_URL, &run[.. 6]);
+    if run.len() > 9 {
+        dir.push_str(&format!("/{:0>3}", &run[9 ..]));
+    }
+    if layout.eq_ignore_ascii_case("PAIRED") {
+        vec![
+            format!("{}/{}/{}_1.fastq.gz", dir, run, run),
+            format!("{}/{}/{}_2.fastq.gz", dir, run, run),
+        ]
+    } else {
+        vec![format!("{}/{}/{}.fastq.gz", dir, run, run)]
+    }
+}
@@ -1,6 +1,14 @@
+// GEO and NCBI record types are named after their accession prefixes (GSE,
+// GPL, SRA, ...), which read better upper-cased.
+#![allow(clippy::upper_case_acronyms)]
+
 use extendr_api::prelude::*;
 
-// mod downloader;
+mod archive;
+mod cache;
+mod downloader;
+mod eutils;
+mod parser;
 mod r;
 mod resolver;
 
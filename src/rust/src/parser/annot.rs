@@ -0,0 +1,100 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, Result};
+
+use super::soft::{push_meta, SoftReader};
+
+/// Separator of multiple values in a cell, e.g. `GO:0005515///GO:0005737`.
+const MULTI_VALUE_SEP: &str = "///";
+
+/// A column of the feature table of a GPL annotation file.
+pub(crate) enum AnnotColumn {
+    /// A single value per feature, as written in the file.
+    Text(Vec<String>),
+    /// Several values per feature, as for Gene Ontology annotations.
+    Multi(Vec<Vec<String>>),
+}
+
+/// A parsed GPL annotation file (`GPLnnn.annot.gz`).
+pub(crate) struct GPLAnnotation {
+    /// `!Annotation_*` lines without the `!`, grouped by name.
+    pub(crate) meta: Vec<(String, Vec<String>)>,
+    /// Header description (`#name = description`) lines.
+    pub(crate) columns: Vec<(String, String)>,
+    /// Feature table, with columns in file order.
+    pub(crate) table: Vec<(String, AnnotColumn)>,
+}
+
+/// Parse a GPL annotation file.
+///
+/// The file starts like a GDS SOFT file, with an `^Annotation` line followed
+/// by `!Annotation_*` attributes and `#column = description` lines, then the
+/// feature table enclosed by `!platform_table_begin` and
+/// `!platform_table_end`:
+/// ```text
+/// ^Annotation
+/// !Annotation_platform = GPL96
+/// #ID = Affymetrix probe set identifier
+/// #GO:Function = Gene Ontology Function term
+/// !platform_table_begin
+/// ID  Gene symbol  Gene ID  GO:Function  ...
+/// ```
+/// Gene Ontology columns (`GO:*`) hold `///`-separated values and are split.
+pub(crate) fn parse_annot<R: BufRead>(reader: R) -> Result<GPLAnnotation> {
+    let mut meta = Vec::new();
+    let mut columns = Vec::new();
+    let mut table = None;
+    for (i, entity) in SoftReader::new(reader).enumerate() {
+        let entity = entity?;
+        if i == 0 && !entity.kind.eq_ignore_ascii_case("Annotation") {
+            return Err(anyhow!(
+                "Expected a GPL annotation file starting with '^Annotation', got '^{}'",
+                entity.kind
+            ));
+        }
+        for (name, values) in entity.meta {
+            for value in values {
+                push_meta(&mut meta, name.clone(), value);
+            }
+        }
+        columns.extend(entity.columns);
+        if table.is_none() {
+            table = entity.table;
+        }
+    }
+    let table = table
+        .map(|table| {
+            table
+                .header
+                .into_iter()
+                .zip(table.columns)
+                .map(|(name, values)| {
+                    let column = if is_multi_value(&name) {
+                        AnnotColumn::Multi(values.iter().map(|cell| split_values(cell)).collect())
+                    } else {
+                        AnnotColumn::Text(values)
+                    };
+                    (name, column)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(GPLAnnotation {
+        meta,
+        columns,
+        table,
+    })
+}
+
+/// Whether the column holds several values per feature.
+fn is_multi_value(name: &str) -> bool {
+    name.starts_with("GO:")
+}
+
+fn split_values(cell: &str) -> Vec<String> {
+    cell.split(MULTI_VALUE_SEP)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+        .collect()
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+
+/// Whether a `filelist.txt` record describes an archive or one of its members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileListKind {
+    Archive,
+    File,
+}
+
+impl fmt::Display for FileListKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            FileListKind::Archive => "Archive",
+            FileListKind::File => "File",
+        })
+    }
+}
+
+/// A record of `filelist.txt`.
+pub(crate) struct FileListRecord {
+    pub(crate) kind: FileListKind,
+    /// The archive holding this file (e.g. `GSE1000_RAW.tar`), `None` for
+    /// archives themselves.
+    pub(crate) archive: Option<String>,
+    pub(crate) name: String,
+    /// Modification time as written by GEO (`MM/DD/YYYY HH:MM:SS`).
+    pub(crate) time: String,
+    pub(crate) size: Option<u64>,
+    /// File type as classified by GEO (e.g. `TAR`, `CEL`, `TXT`).
+    pub(crate) ftype: String,
+}
+
+/// Parse `filelist.txt` found in GEO supplementary file directories.
+///
+/// The file has a header line followed by tab-separated records with columns
+/// `Archive/File`, `Name`, `Time`, `Size` and `Type`, e.g.:
+/// ```text
+/// Archive  GSE1000_RAW.tar  03/12/2008 12:09:00  14680064  TAR
+/// File     GSM15089.CEL.gz  01/11/2005 14:20:35  3566029   CEL
+/// ```
+/// `File` records following an `Archive` record are members of that archive.
+pub(crate) fn parse_filelist(text: &str) -> Result<Vec<FileListRecord>> {
+    let mut archive: Option<String> = None;
+    let mut out = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line.split('\t').collect::<Vec<_>>();
+        if fields.len() < 5 {
+            return Err(anyhow!(
+                "line {}: expected 5 tab-separated fields, got {}",
+                i + 1,
+                fields.len()
+            ));
+        }
+        let kind = match fields[0] {
+            "Archive" => FileListKind::Archive,
+            "File" => FileListKind::File,
+            other => {
+                return Err(anyhow!(
+                    "line {}: expected 'Archive' or 'File', got '{}'",
+                    i + 1,
+                    other
+                ))
+            }
+        };
+        let name = fields[1].to_string();
+        let record = FileListRecord {
+            kind,
+            archive: match kind {
+                FileListKind::Archive => None,
+                FileListKind::File => archive.clone(),
+            },
+            name: name.clone(),
+            time: fields[2].to_string(),
+            size: fields[3].trim().parse().ok(),
+            ftype: fields[4].trim().to_string(),
+        };
+        if kind == FileListKind::Archive {
+            archive = Some(name);
+        }
+        out.push(record);
+    }
+    Ok(out)
+}
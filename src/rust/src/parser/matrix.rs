@@ -0,0 +1,163 @@
+use std::io::BufRead;
+
+use anyhow::{Context, Result};
+
+use super::{read_line, NA_STRINGS};
+
+/// A parsed Series Matrix file (`GSEnnn_series_matrix.txt.gz`).
+pub(crate) struct SeriesMatrix {
+    /// `!Series_*` lines without the prefix, grouped by name in order of
+    /// first appearance.
+    pub(crate) series: Vec<(String, Vec<String>)>,
+    /// `!Sample_*` lines without the prefix, one value per sample. Repeated
+    /// names (e.g. `characteristics_ch1`) are made unique with `.1`, `.2`,
+    /// ... suffixes.
+    pub(crate) sample: Vec<(String, Vec<String>)>,
+    /// Probe identifiers of the data table.
+    pub(crate) rownames: Vec<String>,
+    /// GSM accessions of the data table.
+    pub(crate) colnames: Vec<String>,
+    /// Data table values in row-major order, `NaN` for missing values.
+    pub(crate) values: Vec<f64>,
+    /// Number of non-numeric cells read as missing values.
+    pub(crate) coerced: usize,
+}
+
+impl SeriesMatrix {
+    pub(crate) fn nrow(&self) -> usize {
+        self.rownames.len()
+    }
+
+    pub(crate) fn ncol(&self) -> usize {
+        self.colnames.len()
+    }
+
+    pub(crate) fn get(&self, row: usize, col: usize) -> f64 {
+        self.values[row * self.ncol() + col]
+    }
+}
+
+/// Parse a Series Matrix file.
+///
+/// Header lines are tab-separated with quoted values, e.g.:
+/// ```text
+/// !Series_title  "..."
+/// !Sample_geo_accession  "GSM1"  "GSM2"
+/// ```
+/// The data table is enclosed by `!series_matrix_table_begin` and
+/// `!series_matrix_table_end`, starting with a header of `ID_REF` and the GSM
+/// accessions. Cells that are empty, `NA` or `null` are missing values.
+pub(crate) fn parse_series_matrix<R: BufRead>(mut reader: R) -> Result<SeriesMatrix> {
+    let mut out = SeriesMatrix {
+        series: Vec::new(),
+        sample: Vec::new(),
+        rownames: Vec::new(),
+        colnames: Vec::new(),
+        values: Vec::new(),
+        coerced: 0,
+    };
+    let mut buf = Vec::new();
+    let mut line = String::new();
+    let mut lineno = 0;
+    let mut in_table = false;
+    let mut has_header = false;
+    while read_line(&mut reader, &mut buf, &mut line)
+        .with_context(|| format!("Failed to read line {}", lineno + 1))?
+    {
+        lineno += 1;
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(marker) = line.strip_prefix('!') {
+            match marker.trim_end() {
+                "series_matrix_table_begin" => in_table = true,
+                "series_matrix_table_end" => in_table = false,
+                _ => push_header(&mut out, marker),
+            }
+            continue;
+        }
+        if !in_table {
+            continue;
+        }
+        let mut cells = line.split('\t').map(unquote);
+        if !has_header {
+            // The first column holds the probe identifiers ("ID_REF").
+            cells.next();
+            out.colnames = cells.map(|cell| cell.to_string()).collect();
+            has_header = true;
+            continue;
+        }
+        out.rownames
+            .push(cells.next().unwrap_or_default().to_string());
+        let ncol = out.colnames.len();
+        let mut n = 0;
+        for cell in cells.take(ncol) {
+            let value = if is_na(cell) {
+                f64::NAN
+            } else {
+                cell.parse().unwrap_or_else(|_| {
+                    out.coerced += 1;
+                    f64::NAN
+                })
+            };
+            out.values.push(value);
+            n += 1;
+        }
+        // Short rows are padded with missing values.
+        out.values.extend((n .. ncol).map(|_| f64::NAN));
+    }
+
+    // Every sample line has one value per sample.
+    let nsample = out
+        .sample
+        .iter()
+        .map(|(_, values)| values.len())
+        .max()
+        .unwrap_or(0);
+    for (_, values) in out.sample.iter_mut() {
+        values.resize(nsample, String::new());
+    }
+    Ok(out)
+}
+
+fn push_header(out: &mut SeriesMatrix, line: &str) {
+    let mut fields = line.split('\t');
+    let name = fields.next().unwrap_or_default().trim();
+    let values = fields.map(|field| unquote(field).to_string());
+    if let Some(name) = name.strip_prefix("Series_") {
+        match out.series.iter_mut().find(|(key, _)| key == name) {
+            Some((_, existing)) => existing.extend(values),
+            None => out.series.push((name.to_string(), values.collect())),
+        }
+    } else if let Some(name) = name.strip_prefix("Sample_") {
+        let n = out
+            .sample
+            .iter()
+            .filter(|(key, _)| key == name || is_suffixed(key, name))
+            .count();
+        let name = if n == 0 {
+            name.to_string()
+        } else {
+            format!("{}.{}", name, n)
+        };
+        out.sample.push((name, values.collect()));
+    }
+}
+
+/// Whether `key` is `name` made unique with a numeric suffix (`name.1`).
+fn is_suffixed(key: &str, name: &str) -> bool {
+    key.strip_prefix(name)
+        .and_then(|suffix| suffix.strip_prefix('.'))
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn unquote(cell: &str) -> &str {
+    let cell = cell.trim();
+    cell.strip_prefix('"')
+        .and_then(|cell| cell.strip_suffix('"'))
+        .unwrap_or(cell)
+}
+
+fn is_na(cell: &str) -> bool {
+    cell.is_empty() || NA_STRINGS.contains(&cell)
+}
@@ -0,0 +1,303 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use super::soft::{push_meta, SoftEntity, SoftTable};
+use super::{open_text, read_line};
+use crate::archive::TarExtractor;
+
+/// Reader of MINiML files (`GSEnnn_family.xml.tgz`), giving the same entities
+/// as [`SoftReader`](super::soft::SoftReader).
+///
+/// A MINiML archive holds the main XML document and one `-tbl-N.txt` file per
+/// data table, referenced by the `External-Data` element of each entity.
+/// Element paths are mapped to the attribute names used in SOFT, e.g.
+/// `Sample/Channel[@position=1]/Characteristics[@tag=tissue]` gives
+/// `Sample_characteristics_ch1 = tissue: ...`.
+pub(crate) struct MinimlReader {
+    kinds: Option<Vec<String>>,
+    only_meta: bool,
+}
+
+/// An entity with the file name of its data table, if any.
+struct MinimlEntity {
+    entity: SoftEntity,
+    /// Element name of the entity (e.g. `Sample`), the prefix of attributes.
+    name: String,
+    external: Option<String>,
+}
+
+/// An open XML element.
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    text: String,
+    has_children: bool,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl MinimlReader {
+    pub(crate) fn new() -> Self {
+        Self {
+            kinds: None,
+            only_meta: false,
+        }
+    }
+
+    /// Only parse entities of the given types (case-insensitive).
+    pub(crate) fn kinds(&mut self, kinds: Vec<String>) {
+        self.kinds = Some(kinds)
+    }
+
+    /// Whether to skip data tables (default: false).
+    pub(crate) fn only_meta(&mut self, only_meta: bool) {
+        self.only_meta = only_meta
+    }
+
+    /// Read the MINiML document `path`, either an archive (`.tgz`, `.tar.gz`
+    /// or `.tar`), which is extracted into `exdir` first, or an extracted
+    /// `.xml` file next to its data tables.
+    pub(crate) fn read(&self, path: &Path, exdir: &Path) -> Result<Vec<SoftEntity>> {
+        let name = path.to_string_lossy();
+        let (xml, dir) =
+            if name.ends_with(".tgz") || name.ends_with(".tar.gz") || name.ends_with(".tar") {
+                let members = TarExtractor::new(exdir).extract(path)?;
+                let xml = members
+                    .into_iter()
+                    .map(|member| member.path)
+                    .find(|path| path.extension().is_some_and(|ext| ext == "xml"))
+                    .ok_or_else(|| anyhow!("No XML document found in {}", path.display()))?;
+                (xml, exdir.to_path_buf())
+            } else {
+                let dir = path
+                    .parent()
+                    .map_or_else(PathBuf::new, |dir| dir.to_path_buf());
+                (path.to_path_buf(), dir)
+            };
+        let entities =
+            parse_miniml(&xml).with_context(|| format!("Failed to parse {}", xml.display()))?;
+        entities
+            .into_iter()
+            .filter(|e| {
+                self.kinds.as_ref().map_or(true, |kinds| {
+                    kinds.iter().any(|k| k.eq_ignore_ascii_case(&e.entity.kind))
+                })
+            })
+            .map(|e| self.load_table(e, &dir))
+            .collect()
+    }
+
+    /// Read the external data table of `entity`, using the column names as
+    /// header.
+    fn load_table(&self, entity: MinimlEntity, dir: &Path) -> Result<SoftEntity> {
+        let MinimlEntity {
+            mut entity,
+            external,
+            ..
+        } = entity;
+        let Some(external) = external.filter(|_| !self.only_meta) else {
+            return Ok(entity);
+        };
+        let path = dir.join(&external);
+        let mut reader = open_text(&path)?;
+        let header = entity
+            .columns
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect();
+        let mut table = SoftTable::new(header);
+        let mut buf = Vec::new();
+        let mut line = String::new();
+        while read_line(&mut reader, &mut buf, &mut line)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+        {
+            if !line.is_empty() {
+                table.push_row(&line);
+            }
+        }
+        entity.table = Some(table);
+        Ok(entity)
+    }
+}
+
+/// Parse the main XML document into entities, collecting the names of their
+/// external data tables.
+fn parse_miniml(path: &Path) -> Result<Vec<MinimlEntity>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = Reader::from_reader(BufReader::new(file));
+    reader.config_mut().trim_text(true);
+    let mut buf = Vec::new();
+    let mut stack: Vec<Element> = Vec::new();
+    let mut entities: Vec<MinimlEntity> = Vec::new();
+    // Name and description of the data table column being parsed.
+    let mut column: (String, Vec<String>) = Default::default();
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .with_context(|| format!("Invalid XML at position {}", reader.buffer_position()))?;
+        match event {
+            Event::Start(e) => open_element(&e, &mut stack, &mut entities)?,
+            Event::Empty(e) => {
+                open_element(&e, &mut stack, &mut entities)?;
+                close_element(&mut stack, &mut entities, &mut column);
+            }
+            Event::Text(e) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&e.unescape()?);
+                }
+            }
+            Event::CData(e) => {
+                if let Some(element) = stack.last_mut() {
+                    element
+                        .text
+                        .push_str(&String::from_utf8_lossy(&e.into_inner()));
+                }
+            }
+            Event::End(_) => close_element(&mut stack, &mut entities, &mut column),
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(entities)
+}
+
+fn open_element(
+    e: &BytesStart,
+    stack: &mut Vec<Element>,
+    entities: &mut Vec<MinimlEntity>,
+) -> Result<()> {
+    let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+    let attrs = e
+        .attributes()
+        .map(|attr| {
+            let attr = attr?;
+            Ok((
+                String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned(),
+                attr.unescape_value()?.into_owned(),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(parent) = stack.last_mut() {
+        parent.has_children = true;
+    }
+    let element = Element {
+        name,
+        attrs,
+        text: String::new(),
+        has_children: false,
+    };
+    // Entities are the children of the root `MINiML` element.
+    if stack.len() == 1 {
+        entities.push(MinimlEntity {
+            entity: SoftEntity {
+                kind: element.name.to_uppercase(),
+                accession: element.attr("iid").unwrap_or_default().to_string(),
+                meta: Vec::new(),
+                columns: Vec::new(),
+                table: None,
+            },
+            name: element.name.clone(),
+            external: None,
+        });
+    }
+    stack.push(element);
+    Ok(())
+}
+
+fn close_element(
+    stack: &mut Vec<Element>,
+    entities: &mut [MinimlEntity],
+    column: &mut (String, Vec<String>),
+) {
+    let Some(element) = stack.pop() else {
+        return;
+    };
+    // Only elements nested in an entity give attributes.
+    if stack.len() < 2 {
+        return;
+    }
+    let Some(entity) = entities.last_mut() else {
+        return;
+    };
+    let path = &stack[2 ..];
+    let text = element.text.trim();
+    if element.name == "Data-Table" || path.iter().any(|e| e.name == "Data-Table") {
+        match (element.name.as_str(), stack.last().map(|e| e.name.as_str())) {
+            ("Name", Some("Column")) => column.0 = text.to_string(),
+            ("Description", Some("Column")) => column.1.push(text.to_string()),
+            ("Column", _) => {
+                let (name, description) = std::mem::take(column);
+                entity.entity.columns.push((name, description.join("; ")));
+            }
+            ("External-Data", _) => entity.external = Some(text.to_string()),
+            _ => {}
+        }
+        return;
+    }
+    // Containers such as `<Channel>` hold other elements only.
+    if element.has_children && text.is_empty() {
+        return;
+    }
+    let value = if !text.is_empty() {
+        match (element.attr("tag"), element.name.as_str()) {
+            (Some(tag), "Characteristics") => format!("{}: {}", tag, text),
+            _ => text.to_string(),
+        }
+    } else if let (Some(kind), Some(target)) = (element.attr("type"), element.attr("target")) {
+        // <Relation type="SRA" target="https://..."/>
+        format!("{}: {}", kind, target)
+    } else if let Some(reference) = element.attr("ref") {
+        // <Sample-Ref ref="GSM1"/>
+        reference.to_string()
+    } else {
+        return;
+    };
+    let name = attribute_name(&entity.name, path, &element.name);
+    push_meta(&mut entity.entity.meta, name, value);
+}
+
+/// Build the SOFT attribute name of an element, e.g. `Sample_organism_ch1`.
+fn attribute_name(entity: &str, path: &[Element], name: &str) -> String {
+    let mut parts = vec![entity.to_string()];
+    let mut channel = None;
+    for element in path {
+        match element.name.as_str() {
+            // Containers not written in SOFT attribute names
+            "Status" => {}
+            "Channel" => channel = element.attr("position"),
+            other => parts.push(snake_case(other)),
+        }
+    }
+    parts.push(match name {
+        "Accession" => "geo_accession".to_string(),
+        "Source" => "source_name".to_string(),
+        "Supplementary-Data" => "supplementary_file".to_string(),
+        other => match other.strip_suffix("-Ref") {
+            Some(reference) => format!("{}_id", snake_case(reference)),
+            None => snake_case(other),
+        },
+    });
+    let mut name = parts.join("_");
+    if let Some(channel) = channel {
+        name.push_str("_ch");
+        name.push_str(channel);
+    }
+    name
+}
+
+fn snake_case(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
@@ -0,0 +1,59 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+
+// GPL annotation files (`.annot.gz`)
+pub(crate) mod annot;
+// `filelist.txt` of supplementary file directories
+pub(crate) mod filelist;
+// Series Matrix files
+pub(crate) mod matrix;
+// MINiML files, read into the same entities as SOFT files
+pub(crate) mod miniml;
+// RNA-seq count tables generated by NCBI
+pub(crate) mod rnaseq;
+// run information tables of the SRA
+pub(crate) mod runinfo;
+// SOFT files of all GEO entities
+pub(crate) mod soft;
+
+/// Cells read as missing values in data tables, the same as `na_string` in R.
+pub(crate) const NA_STRINGS: [&str; 4] = ["NA", "null", "NULL", "Null"];
+
+/// Open a text file for buffered reading, decompressing it on the fly if it
+/// is gzip-compressed (detected from the magic bytes, not the extension).
+pub(crate) fn open_text(path: &Path) -> Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let is_gzip = reader
+        .fill_buf()
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .starts_with(&[0x1f, 0x8b]);
+    if is_gzip {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// Read the next line into `line` without the line terminator, replacing
+/// invalid UTF-8. Returns `false` at the end of input.
+pub(crate) fn read_line<R: BufRead + ?Sized>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    line: &mut String,
+) -> std::io::Result<bool> {
+    buf.clear();
+    if reader.read_until(b'\n', buf)? == 0 {
+        return Ok(false);
+    }
+    while matches!(buf.last(), Some(b'\n' | b'\r')) {
+        buf.pop();
+    }
+    line.clear();
+    line.push_str(&String::from_utf8_lossy(buf));
+    Ok(true)
+}
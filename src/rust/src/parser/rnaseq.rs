@@ -0,0 +1,85 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, Context, Result};
+
+use super::{read_line, NA_STRINGS};
+
+/// A gene-by-sample table computed by NCBI for RNA-seq Series
+/// (`GSEnnn_raw_counts_GRCh38.p13_NCBI.tsv.gz` and the normalized variants).
+pub(crate) struct RNASeqTable {
+    /// NCBI Gene identifiers.
+    pub(crate) rownames: Vec<String>,
+    /// GSM accessions.
+    pub(crate) colnames: Vec<String>,
+    /// Values in row-major order, `NaN` for missing values.
+    pub(crate) values: Vec<f64>,
+}
+
+impl RNASeqTable {
+    pub(crate) fn nrow(&self) -> usize {
+        self.rownames.len()
+    }
+
+    pub(crate) fn ncol(&self) -> usize {
+        self.colnames.len()
+    }
+
+    pub(crate) fn get(&self, row: usize, col: usize) -> f64 {
+        self.values[row * self.ncol() + col]
+    }
+}
+
+/// Parse an NCBI-generated RNA-seq table.
+///
+/// The file is tab-separated, with a header of `GeneID` and the GSM
+/// accessions, then one line per gene:
+/// ```text
+/// GeneID  GSM5000001  GSM5000002
+/// 1       12          0
+/// ```
+/// Raw counts are integers, while TPM and FPKM tables hold decimal numbers.
+pub(crate) fn parse_rnaseq<R: BufRead>(mut reader: R) -> Result<RNASeqTable> {
+    let mut out = RNASeqTable {
+        rownames: Vec::new(),
+        colnames: Vec::new(),
+        values: Vec::new(),
+    };
+    let mut buf = Vec::new();
+    let mut line = String::new();
+    let mut lineno = 0;
+    while read_line(&mut reader, &mut buf, &mut line)
+        .with_context(|| format!("Failed to read line {}", lineno + 1))?
+    {
+        lineno += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut cells = line.split('\t').map(|cell| cell.trim());
+        if out.colnames.is_empty() {
+            // The first column holds the gene identifiers ("GeneID").
+            cells.next();
+            out.colnames = cells.map(|cell| cell.to_string()).collect();
+            if out.colnames.is_empty() {
+                return Err(anyhow!("No sample column found in the header line"));
+            }
+            continue;
+        }
+        out.rownames
+            .push(cells.next().unwrap_or_default().to_string());
+        let ncol = out.colnames.len();
+        let mut n = 0;
+        for cell in cells.take(ncol) {
+            let value = if cell.is_empty() || NA_STRINGS.contains(&cell) {
+                f64::NAN
+            } else {
+                cell.parse()
+                    .with_context(|| format!("Invalid value '{}' at line {}", cell, lineno))?
+            };
+            out.values.push(value);
+            n += 1;
+        }
+        // Short rows are padded with missing values.
+        out.values.extend((n .. ncol).map(|_| f64::NAN));
+    }
+    Ok(out)
+}
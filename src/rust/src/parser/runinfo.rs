@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+
+/// A row of the SRA run information table (`efetch ... rettype=runinfo`).
+#[derive(Clone)]
+pub(crate) struct RunInfo {
+    pub(crate) run: String,
+    pub(crate) experiment: String,
+    pub(crate) study: String,
+    pub(crate) sample: String,
+    pub(crate) biosample: String,
+    pub(crate) bioproject: String,
+    /// Sample name given by the submitter, the GSM accession for GEO samples.
+    pub(crate) sample_name: String,
+    /// Number of spots (reads, or read pairs for paired-end runs).
+    pub(crate) spots: Option<u64>,
+    pub(crate) bases: Option<u64>,
+    /// `SINGLE` or `PAIRED`.
+    pub(crate) layout: String,
+    /// e.g. `RNA-Seq`, `ChIP-Seq`.
+    pub(crate) strategy: String,
+    pub(crate) source: String,
+    pub(crate) selection: String,
+    pub(crate) platform: String,
+    pub(crate) model: String,
+    pub(crate) taxid: String,
+    pub(crate) organism: String,
+    /// SRA Normalized download URL.
+    pub(crate) download_path: String,
+}
+
+/// Parse the comma-separated run information table.
+///
+/// Tables fetched in several requests may be concatenated, so repeated header
+/// lines are skipped.
+pub(crate) fn parse_runinfo(text: &str) -> Result<Vec<RunInfo>> {
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let Some(header) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let header = split_csv(header);
+    let index = |name: &str| {
+        header
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| anyhow!("Missing column '{}' in SRA run information", name))
+    };
+    let run = index("Run")?;
+    let experiment = index("Experiment")?;
+    let study = index("SRAStudy")?;
+    let sample = index("Sample")?;
+    let biosample = index("BioSample")?;
+    let bioproject = index("BioProject")?;
+    let sample_name = index("SampleName")?;
+    let spots = index("spots")?;
+    let bases = index("bases")?;
+    let layout = index("LibraryLayout")?;
+    let strategy = index("LibraryStrategy")?;
+    let source = index("LibrarySource")?;
+    let selection = index("LibrarySelection")?;
+    let platform = index("Platform")?;
+    let model = index("Model")?;
+    let taxid = index("TaxID")?;
+    let organism = index("ScientificName")?;
+    let download_path = index("download_path")?;
+
+    let mut out = Vec::new();
+    for line in lines {
+        let fields = split_csv(line);
+        if fields == header {
+            continue;
+        }
+        let field = |i: usize| fields.get(i).cloned().unwrap_or_default();
+        out.push(RunInfo {
+            run: field(run),
+            experiment: field(experiment),
+            study: field(study),
+            sample: field(sample),
+            biosample: field(biosample),
+            bioproject: field(bioproject),
+            sample_name: field(sample_name),
+            spots: field(spots).parse().ok(),
+            bases: field(bases).parse().ok(),
+            layout: field(layout),
+            strategy: field(strategy),
+            source: field(source),
+            selection: field(selection),
+            platform: field(platform),
+            model: field(model),
+            taxid: field(taxid),
+            organism: field(organism),
+            download_path: field(download_path),
+        });
+    }
+    Ok(out)
+}
+
+/// Split a CSV line, honouring double-quoted fields with `""` escapes.
+fn split_csv(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
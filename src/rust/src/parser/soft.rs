@@ -0,0 +1,202 @@
+use std::io::BufRead;
+
+use anyhow::{Context, Result};
+
+use super::read_line;
+
+/// An entity of a SOFT file, started by a caret line such as
+/// `^SAMPLE = GSM11805`.
+pub(crate) struct SoftEntity {
+    /// Entity type as written in the file (e.g. `SERIES`, `SAMPLE`).
+    pub(crate) kind: String,
+    pub(crate) accession: String,
+    /// Attribute (bang) lines, grouped by name in order of first appearance.
+    pub(crate) meta: Vec<(String, Vec<String>)>,
+    /// Data table header description (hash) lines, in file order.
+    pub(crate) columns: Vec<(String, String)>,
+    pub(crate) table: Option<SoftTable>,
+}
+
+/// A data table stored column by column.
+pub(crate) struct SoftTable {
+    pub(crate) header: Vec<String>,
+    pub(crate) columns: Vec<Vec<String>>,
+}
+
+impl SoftTable {
+    pub(crate) fn new(header: Vec<String>) -> Self {
+        let columns = header.iter().map(|_| Vec::new()).collect();
+        Self { header, columns }
+    }
+
+    /// Append a row, padding missing cells and dropping extra ones.
+    pub(crate) fn push_row(&mut self, line: &str) {
+        let mut cells = line.split('\t');
+        for column in self.columns.iter_mut() {
+            column.push(cells.next().unwrap_or("").to_string());
+        }
+    }
+}
+
+/// Streaming reader yielding the entities of a SOFT file one at a time.
+///
+/// There are four different types of line in SOFT, told apart by their first
+/// character:
+///
+/// | Symbol | Line type                           |
+/// | :----: | :---------------------------------- |
+/// |   ^    | entity indicator line               |
+/// |   !    | entity attribute line               |
+/// |   #    | data table header description line |
+/// |  n/a   | data table row                      |
+///
+/// Only the entity being parsed is held in memory.
+pub(crate) struct SoftReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    line: String,
+    /// Entity indicator line read ahead while finishing the previous entity.
+    next: Option<(String, String)>,
+    /// Entity types to parse, all if `None`. Others are skipped.
+    kinds: Option<Vec<String>>,
+    /// Whether to skip data tables.
+    only_meta: bool,
+    lineno: usize,
+}
+
+impl<R: BufRead> SoftReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+            line: String::new(),
+            next: None,
+            kinds: None,
+            only_meta: false,
+            lineno: 0,
+        }
+    }
+
+    /// Only parse entities of the given types (case-insensitive).
+    pub(crate) fn kinds(&mut self, kinds: Vec<String>) {
+        self.kinds = Some(kinds)
+    }
+
+    /// Whether to skip data tables, keeping attribute and header description
+    /// lines only (default: false).
+    pub(crate) fn only_meta(&mut self, only_meta: bool) {
+        self.only_meta = only_meta
+    }
+
+    fn read_line(&mut self) -> Result<bool> {
+        self.lineno += 1;
+        read_line(&mut self.reader, &mut self.buf, &mut self.line)
+            .with_context(|| format!("Failed to read line {}", self.lineno))
+    }
+
+    fn is_wanted(&self, kind: &str) -> bool {
+        self.kinds.as_ref().map_or(true, |kinds| {
+            kinds.iter().any(|k| k.eq_ignore_ascii_case(kind))
+        })
+    }
+
+    /// Read up to the next entity indicator line, returning its type and
+    /// accession, or `None` at the end of input.
+    fn next_indicator(&mut self) -> Result<Option<(String, String)>> {
+        if let Some(indicator) = self.next.take() {
+            return Ok(Some(indicator));
+        }
+        while self.read_line()? {
+            if let Some(indicator) = self.line.strip_prefix('^') {
+                return Ok(Some(split_pair(indicator)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse the body of an entity up to the next entity indicator line.
+    fn read_entity(&mut self, kind: String, accession: String) -> Result<SoftEntity> {
+        let mut entity = SoftEntity {
+            kind,
+            accession,
+            meta: Vec::new(),
+            columns: Vec::new(),
+            table: None,
+        };
+        while self.read_line()? {
+            let line = self.line.as_str();
+            if let Some(indicator) = line.strip_prefix('^') {
+                self.next = Some(split_pair(indicator));
+                break;
+            } else if let Some(attribute) = line.strip_prefix('!') {
+                // `!*_table_begin` and `!*_table_end` only delimit the table
+                if attribute.contains('=') {
+                    let (name, value) = split_pair(attribute);
+                    push_meta(&mut entity.meta, name, value);
+                }
+            } else if let Some(column) = line.strip_prefix('#') {
+                if column.contains('=') {
+                    entity.columns.push(split_pair(column));
+                }
+            } else if !self.only_meta && !line.is_empty() {
+                // The first data line is the header of the table.
+                match entity.table.as_mut() {
+                    Some(table) => table.push_row(line),
+                    None => {
+                        let header = line.split('\t').map(|s| s.to_string()).collect();
+                        entity.table = Some(SoftTable::new(header));
+                    }
+                }
+            }
+        }
+        Ok(entity)
+    }
+
+    /// Skip the body of an entity up to the next entity indicator line.
+    fn skip_entity(&mut self) -> Result<()> {
+        while self.read_line()? {
+            if let Some(indicator) = self.line.strip_prefix('^') {
+                self.next = Some(split_pair(indicator));
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for SoftReader<R> {
+    type Item = Result<SoftEntity>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (kind, accession) = match self.next_indicator() {
+                Ok(Some(indicator)) => indicator,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            if self.is_wanted(&kind) {
+                let context = format!("Failed to parse SOFT entity {} = {}", kind, accession);
+                return Some(self.read_entity(kind, accession).context(context));
+            }
+            if let Err(e) = self.skip_entity() {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Split `name = value` at the first `=`, trimming white spaces around both.
+fn split_pair(line: &str) -> (String, String) {
+    match line.split_once('=') {
+        Some((name, value)) => (name.trim().to_string(), value.trim().to_string()),
+        None => (line.trim().to_string(), String::new()),
+    }
+}
+
+pub(super) fn push_meta(meta: &mut Vec<(String, Vec<String>)>, name: String, value: String) {
+    // Repeated attributes are usually consecutive, search from the end.
+    match meta.iter_mut().rev().find(|(key, _)| *key == name) {
+        Some((_, values)) => values.push(value),
+        None => meta.push((name, vec![value])),
+    }
+}
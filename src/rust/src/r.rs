@@ -3,22 +3,29 @@ use std::result::Result;
 use anyhow::{anyhow, Context};
 use extendr_api::prelude::*;
 
-use super::resolver::{self, GEOEntry, GEOParseError};
+use super::resolver::{self, GEOParseError};
 
+mod archive;
+mod cache;
+mod download;
+mod eutils;
 mod helper;
+mod matrix;
+mod soft;
 
-use helper::resolvers_from_robj;
+use helper::{expand_robj, resolvers_from_robj};
 
 #[extendr]
 fn geo_gtype(accession: Robj, abbre: bool) -> Result<Vec<String>, String> {
-    accession
+    let accession = accession
         .as_str_vector()
         .ok_or_else(|| anyhow!("Expected a character vector"))
-        .with_context(|| format!("Invalid 'accession'"))
-        .map_err(|e| format!("{:?}", e))?
+        .with_context(|| "Invalid 'accession'")
+        .map_err(|e| format!("{:?}", e))?;
+    expand_robj(&accession)?
         .iter()
-        .map(|acc| {
-            resolver::GEOType::try_from(*acc)
+        .map(|(_, acc)| {
+            resolver::GEOType::try_from(acc.as_str())
                 .map(|gtype| {
                     if abbre {
                         gtype.abbre().to_string()
@@ -32,6 +39,78 @@ fn geo_gtype(accession: Robj, abbre: bool) -> Result<Vec<String>, String> {
         .collect::<Result<Vec<String>, String>>()
 }
 
+#[extendr]
+fn geo_expand(accession: Robj) -> Result<Vec<String>, String> {
+    let accession = accession
+        .as_str_vector()
+        .ok_or_else(|| anyhow!("Expected a character vector"))
+        .with_context(|| "Invalid 'accession'")
+        .map_err(|e| format!("{:?}", e))?;
+    expand_robj(&accession).map(|accessions| accessions.into_iter().map(|(_, acc)| acc).collect())
+}
+
+#[extendr]
+fn geo_idtype(accession: Robj, abbre: bool) -> Result<List, String> {
+    let accessions = accession
+        .as_str_vector()
+        .ok_or_else(|| anyhow!("Expected a character vector"))
+        .with_context(|| "Invalid 'accession'")
+        .map_err(|e| format!("{:?}", e))?
+        .iter()
+        .map(|acc| {
+            resolver::Accession::try_from(*acc)
+                .with_context(|| format!("Invalid 'accession': {}", acc))
+                .map_err(|e| format!("{:?}", e))
+        })
+        .collect::<Result<Vec<resolver::Accession>, String>>()?;
+    List::from_names_and_values(["accession", "database", "type"], [
+        Strings::from_values(accessions.iter().map(|acc| acc.accession.as_str())).into_robj(),
+        Strings::from_values(accessions.iter().map(|acc| acc.database().to_string())).into_robj(),
+        Strings::from_values(accessions.iter().map(|acc| {
+            if abbre {
+                acc.atype.abbre().to_string()
+            } else {
+                acc.atype.to_string()
+            }
+        }))
+        .into_robj(),
+    ])
+    .map_err(|e| format!("{:?}", e))
+}
+
+#[extendr]
+fn geo_extract(text: Robj, database: Robj) -> Result<Vec<String>, String> {
+    let text = text
+        .as_str_vector()
+        .ok_or_else(|| anyhow!("Expected a character vector"))
+        .with_context(|| "Invalid 'text'")
+        .map_err(|e| format!("{:?}", e))?;
+    let databases = database
+        .as_str_vector()
+        .ok_or_else(|| anyhow!("Expected a character vector"))
+        .with_context(|| "Invalid 'database'")
+        .map_err(|e| format!("{:?}", e))?
+        .iter()
+        .map(|name| {
+            [
+                resolver::Database::GEO,
+                resolver::Database::SRA,
+                resolver::Database::BioProject,
+                resolver::Database::BioSample,
+            ]
+            .into_iter()
+            .find(|db| db.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow!("Expected one of 'GEO', 'SRA', 'BioProject', or 'BioSample'."))
+            .with_context(|| format!("Invalid 'database': {}", name))
+            .map_err(|e| format!("{:?}", e))
+        })
+        .collect::<Result<Vec<resolver::Database>, String>>()?;
+    Ok(resolver::extract_accessions(&text, &databases)
+        .into_iter()
+        .map(|acc| acc.accession)
+        .collect())
+}
+
 #[extendr]
 fn geo_url(
     accession: Robj,
@@ -48,6 +127,47 @@ fn geo_url(
     })
 }
 
+#[extendr]
+fn geo_parse_url(url: Robj) -> Result<List, String> {
+    let parts = url
+        .as_str_vector()
+        .ok_or_else(|| anyhow!("Expected a character vector"))
+        .with_context(|| "Invalid 'url'")
+        .map_err(|e| format!("{:?}", e))?
+        .iter()
+        .map(|url| {
+            // validate the options by building the resolver
+            let resolver = resolver::GEOResolver::from_url(url).map_err(|e| format!("{:?}", e))?;
+            let mut parts = resolver::parse_geo_url(url).map_err(|e| format!("{:?}", e))?;
+            parts.accession = resolver.accession().to_string();
+            Ok(parts)
+        })
+        .collect::<Result<Vec<resolver::GEOUrlParts>, String>>()?;
+    List::from_names_and_values(["accession", "format", "amount", "scope", "over_https"], [
+        Strings::from_values(parts.iter().map(|p| p.accession.as_str())).into_robj(),
+        Strings::from_values(parts.iter().map(|p| p.format.as_str())).into_robj(),
+        Strings::from_values(
+            parts
+                .iter()
+                .map(|p| p.amount.as_deref().map_or_else(Rstr::na, Rstr::from)),
+        )
+        .into_robj(),
+        Strings::from_values(
+            parts
+                .iter()
+                .map(|p| p.scope.as_deref().map_or_else(Rstr::na, Rstr::from)),
+        )
+        .into_robj(),
+        Logicals::from_values(
+            parts
+                .iter()
+                .map(|p| p.over_https.map_or_else(Rbool::na, Rbool::from)),
+        )
+        .into_robj(),
+    ])
+    .map_err(|e| format!("{:?}", e))
+}
+
 #[extendr]
 fn geo_landing_page(
     accession: &str,
@@ -82,9 +202,37 @@ fn geo_landing_page(
     )
 }
 
+/// Set the base URLs of the GEO endpoints used to build URLs, from
+/// `geo_endpoints()` in R.
+#[extendr]
+fn set_endpoints(
+    adb: &str,
+    gds: &str,
+    ftp_https: &str,
+    ftp: &str,
+    download: &str,
+    sra: &str,
+) -> Result<(), String> {
+    let endpoints = resolver::GEOEndpoints::new(adb, gds, ftp_https, ftp, download, sra)
+        .map_err(|e| format!("{:?}", e))?;
+    resolver::GEOEndpoints::set(endpoints);
+    Ok(())
+}
+
 extendr_module! {
     mod r;
     fn geo_gtype;
+    fn geo_expand;
+    fn geo_idtype;
+    fn geo_extract;
     fn geo_url;
+    fn geo_parse_url;
     fn geo_landing_page;
+    fn set_endpoints;
+    use archive;
+    use cache;
+    use download;
+    use eutils;
+    use matrix;
+    use soft;
 }
@@ -0,0 +1,63 @@
+use std::path::Path;
+use std::result::Result;
+
+use anyhow::Context;
+use extendr_api::prelude::*;
+use regex::Regex;
+
+use crate::archive::TarExtractor;
+
+#[extendr]
+fn geo_untar(
+    tarfile: &str,
+    odir: &str,
+    pattern: Robj,
+    gsm: Robj,
+    gunzip: bool,
+) -> Result<List, String> {
+    let mut extractor = TarExtractor::new(Path::new(odir));
+    if !pattern.is_null() {
+        let pattern = pattern
+            .as_str()
+            .ok_or_else(|| "Invalid 'pattern': expected a string".to_string())?;
+        extractor.pattern(
+            Regex::new(pattern)
+                .with_context(|| "Invalid 'pattern'")
+                .map_err(|e| format!("{:?}", e))?,
+        );
+    }
+    if !gsm.is_null() {
+        extractor.gsm(
+            gsm.as_str_vector()
+                .ok_or_else(|| "Invalid 'gsm': expected a character vector".to_string())?
+                .into_iter()
+                .map(|acc| acc.to_string())
+                .collect(),
+        );
+    }
+    extractor.gunzip(gunzip);
+    let members = extractor
+        .extract(Path::new(tarfile))
+        .map_err(|e| format!("{:?}", e))?;
+    List::from_names_and_values(["member", "path", "gsm"], [
+        Strings::from_values(members.iter().map(|m| m.member.as_str())).into_robj(),
+        Strings::from_values(
+            members
+                .iter()
+                .map(|m| m.path.to_string_lossy().into_owned()),
+        )
+        .into_robj(),
+        Strings::from_values(
+            members
+                .iter()
+                .map(|m| m.gsm.as_deref().map_or_else(Rstr::na, Rstr::from)),
+        )
+        .into_robj(),
+    ])
+    .map_err(|e| format!("{:?}", e))
+}
+
+extendr_module! {
+    mod archive;
+    fn geo_untar;
+}
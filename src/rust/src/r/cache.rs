@@ -0,0 +1,130 @@
+use std::result::Result;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use extendr_api::prelude::*;
+
+use crate::cache::{CacheEntry, GEOCache};
+use crate::downloader::RevalidatePolicy;
+
+#[extendr]
+fn geo_cache_info(root: &str) -> Result<List, String> {
+    let cache = open_cache(root)?;
+    let entries = cache.entries().map_err(|e| format!("{:?}", e))?;
+    entries_to_list(&cache, entries.iter(), None).map_err(|e| format!("{:?}", e))
+}
+
+#[extendr]
+fn geo_cache_prune(root: &str, max_age: Robj, max_size: Robj) -> Result<List, String> {
+    let max_age = robj_to_option_u64(&max_age)
+        .with_context(|| "Invalid 'max_age'")
+        .map_err(|e| format!("{:?}", e))?;
+    let max_size = robj_to_option_u64(&max_size)
+        .with_context(|| "Invalid 'max_size'")
+        .map_err(|e| format!("{:?}", e))?;
+    let cache = open_cache(root)?;
+    let removed = cache
+        .prune(max_age, max_size)
+        .map_err(|e| format!("{:?}", e))?;
+    entries_to_list(&cache, removed.iter(), None).map_err(|e| format!("{:?}", e))
+}
+
+#[extendr]
+fn geo_cache_verify(root: &str) -> Result<List, String> {
+    let cache = open_cache(root)?;
+    let checked = cache.verify().map_err(|e| format!("{:?}", e))?;
+    entries_to_list(
+        &cache,
+        checked.iter().map(|(entry, _)| entry),
+        Some(checked.iter().map(|(_, state)| state.as_str()).collect()),
+    )
+    .map_err(|e| format!("{:?}", e))
+}
+
+pub(super) fn open_cache(root: &str) -> Result<GEOCache, String> {
+    GEOCache::new(root)
+        .with_context(|| "Invalid 'cache'")
+        .map_err(|e| format!("{:?}", e))
+}
+
+/// Build a [`RevalidatePolicy`] from `"never"`, `"always"` or a number of
+/// days.
+pub(super) fn revalidate_from_robj(revalidate: &Robj) -> anyhow::Result<RevalidatePolicy> {
+    if let Some(policy) = revalidate.as_str() {
+        return match policy {
+            "never" => Ok(RevalidatePolicy::Never),
+            "always" => Ok(RevalidatePolicy::Always),
+            _ => Err(anyhow!(
+                "Expected 'never', 'always' or a number of days, got '{}'",
+                policy
+            )),
+        };
+    }
+    revalidate
+        .as_real()
+        .filter(|days| days.is_finite() && *days >= 0.0)
+        .map(|days| RevalidatePolicy::OlderThan(Duration::from_secs_f64(days * 86400.0)))
+        .ok_or_else(|| anyhow!("Expected 'never', 'always' or a number of days"))
+}
+
+fn robj_to_option_u64(value: &Robj) -> anyhow::Result<Option<u64>> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    value
+        .as_real()
+        .filter(|x| x.is_finite() && *x >= 0.0)
+        .map(|x| Some(x as u64))
+        .ok_or_else(|| anyhow!("Expected a non-negative number"))
+}
+
+/// Convert cache entries into a named list of columns, with the `state` of
+/// each entry if given.
+fn entries_to_list<'a>(
+    cache: &GEOCache,
+    entries: impl Iterator<Item = &'a CacheEntry>,
+    state: Option<Vec<&str>>,
+) -> extendr_api::Result<List> {
+    let entries = entries.collect::<Vec<_>>();
+    let optional = |value: &Option<String>| value.as_deref().map_or_else(Rstr::na, Rstr::from);
+    let mut names = vec![
+        "url",
+        "fname",
+        "size",
+        "etag",
+        "last_modified",
+        "sha256",
+        "fetched",
+        "accessed",
+        "path",
+    ];
+    let mut values = vec![
+        Strings::from_values(entries.iter().map(|e| e.url.as_str())).into_robj(),
+        Strings::from_values(entries.iter().map(|e| e.fname.as_str())).into_robj(),
+        // sizes may exceed the range of R integers
+        Doubles::from_values(entries.iter().map(|e| e.size as f64)).into_robj(),
+        Strings::from_values(entries.iter().map(|e| optional(&e.etag))).into_robj(),
+        Strings::from_values(entries.iter().map(|e| optional(&e.last_modified))).into_robj(),
+        Strings::from_values(entries.iter().map(|e| e.sha256.as_str())).into_robj(),
+        Doubles::from_values(entries.iter().map(|e| e.fetched as f64)).into_robj(),
+        Doubles::from_values(entries.iter().map(|e| e.accessed as f64)).into_robj(),
+        Strings::from_values(
+            entries
+                .iter()
+                .map(|e| cache.object_path(&e.sha256).to_string_lossy().into_owned()),
+        )
+        .into_robj(),
+    ];
+    if let Some(state) = state {
+        names.push("state");
+        values.push(Strings::from_values(state).into_robj());
+    }
+    List::from_names_and_values(names, values)
+}
+
+extendr_module! {
+    mod cache;
+    fn geo_cache_info;
+    fn geo_cache_prune;
+    fn geo_cache_verify;
+}
@@ -0,0 +1,464 @@
+use std::path::{Path, PathBuf};
+use std::result::Result;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use extendr_api::prelude::*;
+use regex::Regex;
+
+use super::cache::{open_cache, revalidate_from_robj};
+use super::helper::resolvers_from_robj;
+use crate::downloader::{
+    filelists_resolvers, list_resolvers, matrix_files, outdated_files, DownloadStatus,
+    GEODownloader, ProgressReport, ReplayMode, RetryErrorKind, RetryPolicy,
+};
+use crate::parser::filelist::FileListKind;
+
+#[extendr]
+#[allow(clippy::too_many_arguments)]
+fn geo_download(
+    accession: Robj,
+    format: Robj,
+    amount: Robj,
+    scope: Robj,
+    over_https: Robj,
+    odir: &str,
+    concurrency: i32,
+    resume: bool,
+    retry: Robj,
+    cache: Robj,
+    revalidate: Robj,
+    progress: Robj,
+) -> Result<List, String> {
+    let resolvers = resolvers_from_robj(&accession, &format, &amount, &scope, &over_https)?;
+    let retry = retry_from_robj(&retry)
+        .with_context(|| "Invalid 'retry'")
+        .map_err(|e| format!("{:?}", e))?;
+    let mut downloader = GEODownloader::new(concurrency.max(1) as usize);
+    downloader.resume(resume);
+    downloader.retry(retry);
+    use_cache(&mut downloader, &cache, &revalidate)?;
+    let odir = Path::new(odir);
+    for resolver in resolvers.iter() {
+        downloader
+            .collect(resolver, odir)
+            .map_err(|e| format!("{:?}", e))?;
+    }
+    let status = download_reporting(downloader, &progress)
+        .with_context(|| "Failed to download GEO files")
+        .map_err(|e| format!("{:?}", e))?;
+    status_to_list(status).map_err(|e| format!("{:?}", e))
+}
+
+/// Download `urls` into `destfiles` with the native engine, used by
+/// `download_inform()` for the downloads of `geo()` and friends.
+#[extendr]
+fn download_urls(
+    urls: Vec<String>,
+    destfiles: Vec<String>,
+    concurrency: i32,
+    retry: Robj,
+    cache: Robj,
+    revalidate: Robj,
+    progress: Robj,
+) -> Result<List, String> {
+    let retry = retry_from_robj(&retry)
+        .with_context(|| "Invalid 'retry'")
+        .map_err(|e| format!("{:?}", e))?;
+    let mut downloader = GEODownloader::new(concurrency.max(1) as usize);
+    downloader.retry(retry);
+    use_cache(&mut downloader, &cache, &revalidate)?;
+    for (url, destfile) in urls.into_iter().zip(destfiles) {
+        downloader.push(url, PathBuf::from(destfile));
+    }
+    let status = download_reporting(downloader, &progress)
+        .with_context(|| "Failed to download GEO files")
+        .map_err(|e| format!("{:?}", e))?;
+    status_to_list(status).map_err(|e| format!("{:?}", e))
+}
+
+/// Whether each of `destfiles`, downloaded before from `urls`, is outdated,
+/// checking with the server those due under `revalidate`. Used by
+/// `download_inform()` for files already in the output directory.
+#[extendr]
+fn local_outdated(
+    urls: Vec<String>,
+    destfiles: Vec<String>,
+    revalidate: Robj,
+) -> Result<Vec<bool>, String> {
+    let revalidate = revalidate_from_robj(&revalidate)
+        .with_context(|| "Invalid 'revalidate'")
+        .map_err(|e| format!("{:?}", e))?;
+    let files = urls
+        .into_iter()
+        .zip(destfiles.into_iter().map(PathBuf::from))
+        .collect();
+    outdated_files(files, revalidate).map_err(|e| format!("{:?}", e))
+}
+
+/// Serve and store the files of `downloader` in the cache rooted at `cache`,
+/// unless it is `NULL`, checking cached files with the server as asked by
+/// `revalidate`.
+fn use_cache(
+    downloader: &mut GEODownloader,
+    cache: &Robj,
+    revalidate: &Robj,
+) -> Result<(), String> {
+    if cache.is_null() {
+        return Ok(());
+    }
+    let root = cache
+        .as_str()
+        .ok_or_else(|| "Invalid 'cache': expected a string".to_string())?;
+    downloader.cache(open_cache(root)?);
+    downloader.revalidate(
+        revalidate_from_robj(revalidate)
+            .with_context(|| "Invalid 'revalidate'")
+            .map_err(|e| format!("{:?}", e))?,
+    );
+    Ok(())
+}
+
+/// Minimal time between two progress reports sent to R.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Download all files of `downloader`, passing the progress to the R function
+/// `progress` if it is not `NULL`.
+///
+/// The function is called from the R main thread only, with a list of the
+/// aggregate counters, a one-line `status` summary and the `files` counters.
+/// Errors raised by the function are ignored, so that a faulty reporter never
+/// interrupts the transfers.
+fn download_reporting(
+    downloader: GEODownloader,
+    progress: &Robj,
+) -> anyhow::Result<Vec<DownloadStatus>> {
+    if progress.is_null() {
+        return downloader.download();
+    }
+    let progress = progress
+        .as_function()
+        .ok_or_else(|| anyhow!("Expected a function or NULL"))
+        .with_context(|| "Invalid 'progress'")?;
+    downloader.download_with_progress(PROGRESS_INTERVAL, |report| {
+        if let Ok(report) = report_to_list(report) {
+            let _ = progress.call(pairlist!(report = report));
+        }
+    })
+}
+
+fn report_to_list(report: &ProgressReport) -> extendr_api::Result<List> {
+    // sizes may exceed the range of R integers
+    let size = |size: Option<u64>| size.map(|x| x as f64);
+    let files = List::from_names_and_values(["name", "received", "total", "state"], [
+        Strings::from_values(report.files.iter().map(|f| f.name.as_str())).into_robj(),
+        Doubles::from_values(report.files.iter().map(|f| f.received as f64)).into_robj(),
+        Doubles::from_values(report.files.iter().map(|f| size(f.total))).into_robj(),
+        Strings::from_values(report.files.iter().map(|f| f.state.as_str())).into_robj(),
+    ])?;
+    List::from_names_and_values(
+        [
+            "received", "total", "rate", "eta", "done", "status", "files",
+        ],
+        [
+            Robj::from(report.received() as f64),
+            Robj::from(size(report.total())),
+            Robj::from(report.rate),
+            Robj::from(report.eta().map(|eta| eta.as_secs_f64())),
+            Robj::from(report.done),
+            Robj::from(report.summary()),
+            files.into_robj(),
+        ],
+    )
+}
+
+/// Set how network access is served, from `geo_replay()` in R: `"live"`,
+/// or `"record"` and `"replay"` with the fixture directory `dir`.
+#[extendr]
+fn set_replay(mode: &str, dir: &str) -> Result<(), String> {
+    let mode = match mode {
+        "live" => ReplayMode::Live,
+        "record" => ReplayMode::Record(PathBuf::from(dir)),
+        "replay" => ReplayMode::Replay(PathBuf::from(dir)),
+        _ => {
+            return Err(format!(
+                "Invalid replay mode: expected 'live', 'record' or 'replay', got '{}'",
+                mode
+            ))
+        }
+    };
+    ReplayMode::set(mode);
+    Ok(())
+}
+
+#[extendr]
+fn geo_list(
+    accession: Robj,
+    format: Robj,
+    over_https: Robj,
+    recursive: bool,
+) -> Result<List, String> {
+    let null = Robj::from(());
+    let resolvers = resolvers_from_robj(&accession, &format, &null, &null, &over_https)?;
+    let listings = list_resolvers(&resolvers, recursive).map_err(|e| format!("{:?}", e))?;
+    let entries = resolvers
+        .iter()
+        .zip(listings.iter())
+        .flat_map(|(resolver, entries)| entries.iter().map(move |e| (resolver.accession(), e)))
+        .collect::<Vec<_>>();
+    List::from_names_and_values(
+        [
+            "accession",
+            "name",
+            "url",
+            "size",
+            "last_modified",
+            "is_dir",
+        ],
+        [
+            Strings::from_values(entries.iter().map(|(acc, _)| *acc)).into_robj(),
+            Strings::from_values(entries.iter().map(|(_, e)| e.name.as_str())).into_robj(),
+            Strings::from_values(entries.iter().map(|(_, e)| e.url.as_str())).into_robj(),
+            // sizes may exceed the range of R integers
+            Doubles::from_values(entries.iter().map(|(_, e)| e.size.map(|x| x as f64))).into_robj(),
+            Strings::from_values(
+                entries
+                    .iter()
+                    .map(|(_, e)| e.modified.as_deref().map_or_else(Rstr::na, Rstr::from)),
+            )
+            .into_robj(),
+            Logicals::from_values(entries.iter().map(|(_, e)| e.is_dir)).into_robj(),
+        ],
+    )
+    .map_err(|e| format!("{:?}", e))
+}
+
+#[extendr]
+fn geo_filelist(
+    accession: Robj,
+    over_https: Robj,
+    pattern: Robj,
+    ftype: Robj,
+) -> Result<List, String> {
+    let null = Robj::from(());
+    let format = Robj::from("suppl");
+    let resolvers = resolvers_from_robj(&accession, &format, &null, &null, &over_https)?;
+    let pattern = if pattern.is_null() {
+        None
+    } else {
+        let pattern = pattern
+            .as_str()
+            .ok_or_else(|| "Invalid 'pattern': expected a string".to_string())?;
+        Some(
+            Regex::new(pattern)
+                .with_context(|| "Invalid 'pattern'")
+                .map_err(|e| format!("{:?}", e))?,
+        )
+    };
+    let ftype = if ftype.is_null() {
+        None
+    } else {
+        Some(
+            ftype
+                .as_str_vector()
+                .ok_or_else(|| "Invalid 'type': expected a character vector".to_string())?,
+        )
+    };
+    let filelists = filelists_resolvers(&resolvers).map_err(|e| format!("{:?}", e))?;
+    let records = resolvers
+        .iter()
+        .zip(filelists.iter())
+        .flat_map(|(resolver, records)| records.iter().map(move |r| (resolver.accession(), r)))
+        // only keep archive members, archives themselves are listed by `geo_list()`
+        .filter(|(_, r)| r.kind == FileListKind::File)
+        .filter(|(_, r)| pattern.as_ref().map_or(true, |re| re.is_match(&r.name)))
+        .filter(|(_, r)| {
+            ftype.as_ref().map_or(true, |types| {
+                types.iter().any(|t| t.eq_ignore_ascii_case(&r.ftype))
+            })
+        })
+        .collect::<Vec<_>>();
+    List::from_names_and_values(["accession", "archive", "name", "time", "size", "type"], [
+        Strings::from_values(records.iter().map(|(acc, _)| *acc)).into_robj(),
+        Strings::from_values(
+            records
+                .iter()
+                .map(|(_, r)| r.archive.as_deref().map_or_else(Rstr::na, Rstr::from)),
+        )
+        .into_robj(),
+        Strings::from_values(records.iter().map(|(_, r)| r.name.as_str())).into_robj(),
+        Strings::from_values(records.iter().map(|(_, r)| r.time.as_str())).into_robj(),
+        Doubles::from_values(records.iter().map(|(_, r)| r.size.map(|x| x as f64))).into_robj(),
+        Strings::from_values(records.iter().map(|(_, r)| r.ftype.as_str())).into_robj(),
+    ])
+    .map_err(|e| format!("{:?}", e))
+}
+
+#[extendr]
+fn geo_matrix_files(accession: Robj, platform: Robj, over_https: Robj) -> Result<List, String> {
+    let null = Robj::from(());
+    let format = Robj::from("matrix");
+    let resolvers = resolvers_from_robj(&accession, &format, &null, &null, &over_https)?;
+    let platform = if platform.is_null() {
+        None
+    } else {
+        Some(
+            platform
+                .as_str()
+                .ok_or_else(|| "Invalid 'platform': expected a string".to_string())?,
+        )
+    };
+    let listings = matrix_files(&resolvers, platform).map_err(|e| format!("{:?}", e))?;
+    let files = resolvers
+        .iter()
+        .zip(listings.iter())
+        .flat_map(|(resolver, files)| files.iter().map(move |f| (resolver.accession(), f)))
+        .collect::<Vec<_>>();
+    List::from_names_and_values(["accession", "platform", "name", "url", "size"], [
+        Strings::from_values(files.iter().map(|(acc, _)| *acc)).into_robj(),
+        Strings::from_values(
+            files
+                .iter()
+                .map(|(_, f)| f.platform.as_deref().map_or_else(Rstr::na, Rstr::from)),
+        )
+        .into_robj(),
+        Strings::from_values(files.iter().map(|(_, f)| f.name.as_str())).into_robj(),
+        Strings::from_values(files.iter().map(|(_, f)| f.url.as_str())).into_robj(),
+        Doubles::from_values(files.iter().map(|(_, f)| f.size.map(|x| x as f64))).into_robj(),
+    ])
+    .map_err(|e| format!("{:?}", e))
+}
+
+/// Build a [`RetryPolicy`] from the list created by `geo_retry()` in R.
+///
+/// Missing fields keep their default value and `NULL` gives the default policy.
+pub(super) fn retry_from_robj(retry: &Robj) -> anyhow::Result<RetryPolicy> {
+    let mut policy = RetryPolicy::default();
+    if retry.is_null() {
+        return Ok(policy);
+    }
+    let list = retry.as_list().ok_or_else(|| anyhow!("Expected a list"))?;
+    for (name, value) in list.iter() {
+        match name {
+            "max_attempts" => {
+                policy.max_attempts = value
+                    .as_integer()
+                    .filter(|n| *n >= 1)
+                    .ok_or_else(|| anyhow!("'max_attempts' must be a positive integer"))?
+                    as u32;
+            }
+            "base_delay" => policy.base_delay = robj_to_duration(&value, name)?,
+            "max_delay" => policy.max_delay = robj_to_duration(&value, name)?,
+            "jitter" => {
+                policy.jitter = value
+                    .as_real()
+                    .filter(|x| (0.0 ..= 1.0).contains(x))
+                    .ok_or_else(|| anyhow!("'jitter' must be a number between 0 and 1"))?;
+            }
+            "status" => {
+                policy.status = value
+                    .as_integer_slice()
+                    .ok_or_else(|| anyhow!("'status' must be an integer vector"))?
+                    .iter()
+                    .map(|code| {
+                        u16::try_from(*code)
+                            .ok()
+                            .filter(|code| (100 ..= 599).contains(code))
+                            .ok_or_else(|| {
+                                // NA_integer_ is the smallest i32
+                                let code = match *code {
+                                    i32::MIN => "NA".to_string(),
+                                    code => code.to_string(),
+                                };
+                                anyhow!("'status' must hold HTTP status codes, got {}", code)
+                            })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+            }
+            "errors" => {
+                policy.errors = value
+                    .as_str_vector()
+                    .ok_or_else(|| anyhow!("'errors' must be a character vector"))?
+                    .into_iter()
+                    .map(|kind| kind.parse::<RetryErrorKind>())
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+            }
+            _ => return Err(anyhow!("Unknown field '{}'", name)),
+        }
+    }
+    Ok(policy)
+}
+
+fn robj_to_duration(value: &Robj, name: &str) -> anyhow::Result<Duration> {
+    value
+        .as_real()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| anyhow!("'{}' must be a non-negative number of seconds", name))
+}
+
+/// Convert download status into a named list with the per-file columns, and
+/// the log of failed attempts in `attempts`.
+pub(super) fn status_to_list(status: Vec<DownloadStatus>) -> extendr_api::Result<List> {
+    let files = List::from_names_and_values(
+        [
+            "url", "destfile", "success", "status", "error", "attempts", "cached", "outdated",
+        ],
+        [
+            Strings::from_values(status.iter().map(|s| s.url.as_str())).into_robj(),
+            Strings::from_values(status.iter().map(|s| s.destfile.as_str())).into_robj(),
+            Logicals::from_values(status.iter().map(|s| s.success)).into_robj(),
+            Integers::from_values(status.iter().map(|s| s.status.map(|code| code as i32)))
+                .into_robj(),
+            Strings::from_values(
+                status
+                    .iter()
+                    .map(|s| s.error.as_deref().map_or_else(Rstr::na, Rstr::from)),
+            )
+            .into_robj(),
+            Integers::from_values(status.iter().map(|s| s.attempts() as i32)).into_robj(),
+            Logicals::from_values(status.iter().map(|s| s.cached)).into_robj(),
+            Logicals::from_values(status.iter().map(|s| s.outdated)).into_robj(),
+        ],
+    )?;
+
+    let failures = status
+        .iter()
+        .flat_map(|s| {
+            s.failures
+                .iter()
+                .enumerate()
+                .map(move |(i, failure)| (s.url.as_str(), i + 1, failure))
+        })
+        .collect::<Vec<_>>();
+    let attempts = List::from_names_and_values(["url", "attempt", "status", "error", "wait"], [
+        Strings::from_values(failures.iter().map(|(url, _, _)| *url)).into_robj(),
+        Integers::from_values(failures.iter().map(|(_, i, _)| *i as i32)).into_robj(),
+        Integers::from_values(
+            failures
+                .iter()
+                .map(|(_, _, f)| f.status.map(|code| code as i32)),
+        )
+        .into_robj(),
+        Strings::from_values(failures.iter().map(|(_, _, f)| f.error.as_str())).into_robj(),
+        Doubles::from_values(
+            failures
+                .iter()
+                .map(|(_, _, f)| f.wait.map(|wait| wait.as_secs_f64())),
+        )
+        .into_robj(),
+    ])?;
+
+    List::from_names_and_values(["files", "attempts"], [files, attempts])
+}
+
+extendr_module! {
+    mod download;
+    fn geo_download;
+    fn download_urls;
+    fn local_outdated;
+    fn set_replay;
+    fn geo_list;
+    fn geo_filelist;
+    fn geo_matrix_files;
+}
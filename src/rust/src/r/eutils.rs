@@ -0,0 +1,181 @@
+use std::result::Result;
+
+use anyhow::Context;
+use extendr_api::prelude::*;
+
+use super::helper::resolvers_from_robj;
+use crate::eutils::{link_geo, search_gds, sra_runs, GDSSummary, SRARun};
+
+#[extendr]
+fn geo_search(query: &str, step: i32, api_key: Option<String>) -> Result<List, String> {
+    let records = search_gds(query, step.max(1) as usize, api_key)
+        .with_context(|| "Failed to search the GEO database")
+        .map_err(|e| format!("{:?}", e))?;
+    summaries_to_list(&records).map_err(|e| format!("{:?}", e))
+}
+
+#[extendr]
+fn geo_link(
+    accession: Vec<String>,
+    target_db: Vec<String>,
+    api_key: Option<String>,
+) -> Result<List, String> {
+    let (links, unresolved) = link_geo(&accession, &target_db, api_key)
+        .with_context(|| "Failed to link GEO accessions")
+        .map_err(|e| format!("{:?}", e))?;
+    let links =
+        List::from_names_and_values(["accession", "link_name", "target_db", "target_id"], [
+            Strings::from_values(links.iter().map(|l| l.accession.as_str())).into_robj(),
+            Strings::from_values(links.iter().map(|l| l.link_name.as_str())).into_robj(),
+            Strings::from_values(links.iter().map(|l| l.target_db.as_str())).into_robj(),
+            Strings::from_values(links.iter().map(|l| l.target_id.as_str())).into_robj(),
+        ])
+        .map_err(|e| format!("{:?}", e))?;
+    List::from_names_and_values(["links", "unresolved"], [
+        links.into_robj(),
+        Strings::from_values(unresolved.iter().map(|acc| acc.as_str())).into_robj(),
+    ])
+    .map_err(|e| format!("{:?}", e))
+}
+
+#[extendr]
+fn geo_sra(accession: Robj, api_key: Option<String>) -> Result<List, String> {
+    let null = Robj::from(());
+    let format = Robj::from("sra");
+    let resolvers = resolvers_from_robj(&accession, &format, &null, &null, &null)?;
+    let accessions: Vec<String> = resolvers
+        .iter()
+        .map(|resolver| resolver.accession().to_string())
+        .collect();
+    let (runs, unresolved) = sra_runs(&accessions, api_key)
+        .with_context(|| "Failed to find SRA runs")
+        .map_err(|e| format!("{:?}", e))?;
+    let runs = runs_to_list(&runs).map_err(|e| format!("{:?}", e))?;
+    List::from_names_and_values(["runs", "unresolved"], [
+        runs.into_robj(),
+        Strings::from_values(unresolved.iter().map(|acc| acc.as_str())).into_robj(),
+    ])
+    .map_err(|e| format!("{:?}", e))
+}
+
+/// Convert runs into a list of columns; fastq URLs are a list column.
+fn runs_to_list(runs: &[SRARun]) -> extendr_api::Result<List> {
+    let strings =
+        |field: fn(&SRARun) -> &str| Strings::from_values(runs.iter().map(field)).into_robj();
+    // counts may exceed the range of R integers
+    let counts = |field: fn(&SRARun) -> Option<u64>| {
+        Doubles::from_values(runs.iter().map(|run| field(run).map(|n| n as f64))).into_robj()
+    };
+    List::from_names_and_values(
+        [
+            "accession",
+            "gsm",
+            "run",
+            "experiment",
+            "study",
+            "sample",
+            "biosample",
+            "bioproject",
+            "spots",
+            "bases",
+            "layout",
+            "strategy",
+            "source",
+            "selection",
+            "platform",
+            "model",
+            "taxid",
+            "organism",
+            "sra_url",
+            "fastq_url",
+        ],
+        [
+            strings(|r| &r.accession),
+            Strings::from_values(
+                runs.iter()
+                    .map(|r| r.gsm.as_deref().map_or_else(Rstr::na, Rstr::from)),
+            )
+            .into_robj(),
+            strings(|r| &r.info.run),
+            strings(|r| &r.info.experiment),
+            strings(|r| &r.info.study),
+            strings(|r| &r.info.sample),
+            strings(|r| &r.info.biosample),
+            strings(|r| &r.info.bioproject),
+            counts(|r| r.info.spots),
+            counts(|r| r.info.bases),
+            strings(|r| &r.info.layout),
+            strings(|r| &r.info.strategy),
+            strings(|r| &r.info.source),
+            strings(|r| &r.info.selection),
+            strings(|r| &r.info.platform),
+            strings(|r| &r.info.model),
+            strings(|r| &r.info.taxid),
+            strings(|r| &r.info.organism),
+            strings(|r| &r.info.download_path),
+            List::from_values(
+                runs.iter()
+                    .map(|r| Strings::from_values(r.fastq.iter().map(|url| url.as_str()))),
+            )
+            .into_robj(),
+        ],
+    )
+}
+
+/// Convert summaries into a list of columns; linked accessions are list
+/// columns of character vectors.
+fn summaries_to_list(records: &[GDSSummary]) -> extendr_api::Result<List> {
+    let strings = |field: fn(&GDSSummary) -> &str| {
+        Strings::from_values(records.iter().map(field)).into_robj()
+    };
+    let links = |field: fn(&GDSSummary) -> &Vec<String>| {
+        List::from_values(
+            records
+                .iter()
+                .map(|record| Strings::from_values(field(record).iter().map(|x| x.as_str()))),
+        )
+        .into_robj()
+    };
+    List::from_names_and_values(
+        [
+            "accession",
+            "title",
+            "summary",
+            "taxon",
+            "entry_type",
+            "gds_type",
+            "gpl",
+            "gse",
+            "gsm",
+            "n_samples",
+            "pdat",
+            "uid",
+        ],
+        [
+            strings(|r| &r.accession),
+            strings(|r| &r.title),
+            strings(|r| &r.summary),
+            strings(|r| &r.taxon),
+            strings(|r| &r.entry_type),
+            strings(|r| &r.gds_type),
+            links(|r| &r.gpl),
+            links(|r| &r.gse),
+            links(|r| &r.gsm),
+            Integers::from_values(
+                records
+                    .iter()
+                    .map(|r| r.n_samples.map_or_else(Rint::na, |n| Rint::from(n as i32))),
+            )
+            .into_robj(),
+            strings(|r| &r.pdat),
+            strings(|r| &r.uid),
+        ],
+    )
+}
+
+extendr_module! {
+    mod eutils;
+    fn geo_search;
+    fn geo_link;
+    fn geo_sra;
+}
@@ -1,6 +1,7 @@
 use anyhow::{anyhow, Context, Result};
 use extendr_api::prelude::*;
 
+use crate::parser::NA_STRINGS;
 use crate::resolver;
 
 pub(super) fn resolvers_from_robj(
@@ -13,35 +14,48 @@ pub(super) fn resolvers_from_robj(
     let accession = accession
         .as_str_vector()
         .ok_or_else(|| anyhow!("Expected a character vector"))
-        .with_context(|| format!("Invalid 'accession'"))
+        .with_context(|| "Invalid 'accession'")
         .map_err(|e| format!("{:?}", e))?;
-    let format = robj_to_vec_str(&format, accession.len())
-        .with_context(|| format!("Invalid 'format'"))
+    let format = robj_to_vec_str(format, accession.len())
+        .with_context(|| "Invalid 'format'")
         .map_err(|e| format!("{:?}", e))?;
-    let amount = robj_to_option_vec_str(&amount, accession.len())
-        .with_context(|| format!("Invalid 'amount'"))
+    let amount = robj_to_option_vec_str(amount, accession.len())
+        .with_context(|| "Invalid 'amount'")
         .map_err(|e| format!("{:?}", e))?;
-    let scope = robj_to_option_vec_str(&scope, accession.len())
-        .with_context(|| format!("Invalid 'scope'"))
+    let scope = robj_to_option_vec_str(scope, accession.len())
+        .with_context(|| "Invalid 'scope'")
         .map_err(|e| format!("{:?}", e))?;
-    let over_https = robj_to_option_vec_bool(&over_https, accession.len())
-        .with_context(|| format!("Invalid 'over_https'"))
+    let over_https = robj_to_option_vec_bool(over_https, accession.len())
+        .with_context(|| "Invalid 'over_https'")
         .map_err(|e| format!("{:?}", e))?;
-    accession
+    expand_robj(&accession)?
         .into_iter()
-        .enumerate()
         .map(|(i, acc)| {
             let format = format[i];
             let amount = amount.as_ref().map(|v| v[i]);
             let scope = scope.as_ref().map(|v| v[i]);
             let over_https = over_https.as_ref().map(|v| v[i]);
-            resolver::GEOResolver::new(acc, format, amount, scope, over_https)
+            resolver::GEOResolver::new(&acc, format, amount, scope, over_https)
                 .map_err(|e| format!("{:?}", e))
         })
         .collect()
 }
 
-fn robj_to_vec_str<'a>(value: &'a Robj, len: usize) -> Result<Vec<&'a str>> {
+/// Expand the accession lists and ranges of each element of `accession` (see
+/// [`resolver::expand_accessions`]), pairing each accession with the index of
+/// the element it comes from.
+pub(super) fn expand_robj(accession: &[&str]) -> Result<Vec<(usize, String)>, String> {
+    let mut out = Vec::new();
+    for (i, expr) in accession.iter().enumerate() {
+        let expanded = resolver::expand_accessions(expr)
+            .with_context(|| format!("Invalid 'accession': {}", expr))
+            .map_err(|e| format!("{:?}", e))?;
+        out.extend(expanded.into_iter().map(|acc| (i, acc)));
+    }
+    Ok(out)
+}
+
+fn robj_to_vec_str(value: &Robj, len: usize) -> Result<Vec<&str>> {
     let value = value
         .as_str_vector()
         .ok_or_else(|| anyhow!("Expected a character vector"))?;
@@ -57,11 +71,11 @@ fn robj_to_vec_str<'a>(value: &'a Robj, len: usize) -> Result<Vec<&'a str>> {
     }
 }
 
-fn robj_to_option_vec_str<'a>(value: &'a Robj, len: usize) -> Result<Option<Vec<&'a str>>> {
+fn robj_to_option_vec_str(value: &Robj, len: usize) -> Result<Option<Vec<&str>>> {
     if value.is_null() {
         return Ok(None);
     }
-    robj_to_vec_str(value, len).map(|o| Some(o))
+    robj_to_vec_str(value, len).map(Some)
 }
 
 fn robj_to_option_vec_bool(value: &Robj, len: usize) -> Result<Option<Vec<bool>>> {
@@ -93,3 +107,55 @@ fn robj_to_option_vec_bool(value: &Robj, len: usize) -> Result<Option<Vec<bool>>
         )),
     }
 }
+
+/// Convert a data table column into an R vector, guessing its type like
+/// `data.table::fread()`: integer, `bit64::integer64` for integers out of the
+/// range of R integers, double, or character otherwise. Empty cells are
+/// missing in numeric columns but kept as is in character columns.
+pub(super) fn column_to_robj(values: &[String]) -> Robj {
+    let is_na = |x: &str| x.is_empty() || NA_STRINGS.contains(&x);
+    if !values.is_empty() && values.iter().all(|x| is_na(x)) {
+        return Logicals::from_values(values.iter().map(|_| Rbool::na())).into_robj();
+    }
+    // `i32::MIN` is `NA_integer_` in R
+    let as_integer = |x: &str| x.parse::<i32>().ok().filter(|x| *x != i32::MIN);
+    if values.iter().all(|x| is_na(x) || as_integer(x).is_some()) {
+        return Integers::from_values(
+            values
+                .iter()
+                .map(|x| as_integer(x).map_or_else(Rint::na, Rint::from)),
+        )
+        .into_robj();
+    }
+    // `integer64` vectors hold the bits of each `i64` in a double, with
+    // `i64::MIN` as `NA`
+    let as_integer64 = |x: &str| x.parse::<i64>().ok().filter(|x| *x != i64::MIN);
+    if values.iter().all(|x| is_na(x) || as_integer64(x).is_some()) {
+        let mut column = Doubles::from_values(
+            values
+                .iter()
+                .map(|x| f64::from_bits(as_integer64(x).unwrap_or(i64::MIN) as u64)),
+        )
+        .into_robj();
+        column
+            .set_class(["integer64"])
+            .expect("Failed to set the class of an integer64 column");
+        return column;
+    }
+    if values.iter().all(|x| is_na(x) || x.parse::<f64>().is_ok()) {
+        return Doubles::from_values(
+            values
+                .iter()
+                .map(|x| x.parse::<f64>().map_or_else(|_| Rfloat::na(), Rfloat::from)),
+        )
+        .into_robj();
+    }
+    Strings::from_values(values.iter().map(|x| {
+        if NA_STRINGS.contains(&x.as_str()) {
+            Rstr::na()
+        } else {
+            Rstr::from(x.as_str())
+        }
+    }))
+    .into_robj()
+}
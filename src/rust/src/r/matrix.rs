@@ -0,0 +1,124 @@
+use std::path::Path;
+use std::result::Result;
+
+use anyhow::Context;
+use extendr_api::prelude::*;
+
+use crate::parser::matrix::parse_series_matrix;
+use crate::parser::open_text;
+use crate::parser::rnaseq::parse_rnaseq;
+
+/// Parse a Series Matrix file into
+/// `list(series, sample, matrix, coerced)`, where `matrix` is a double matrix
+/// with probe rownames and GSM colnames.
+#[extendr]
+fn read_series_matrix(path: &str) -> Result<List, String> {
+    let matrix = open_text(Path::new(path))
+        .and_then(parse_series_matrix)
+        .with_context(|| format!("Failed to parse {}", path))
+        .map_err(|e| format!("{:?}", e))?;
+    let series = List::from_names_and_values(
+        matrix.series.iter().map(|(name, _)| name.as_str()),
+        matrix
+            .series
+            .iter()
+            .map(|(_, values)| Strings::from_values(values.iter().map(|v| v.as_str())).into_robj()),
+    )
+    .map_err(|e| format!("{:?}", e))?;
+    let sample = List::from_names_and_values(
+        matrix.sample.iter().map(|(name, _)| name.as_str()),
+        matrix
+            .sample
+            .iter()
+            .map(|(_, values)| Strings::from_values(values.iter().map(|v| v.as_str())).into_robj()),
+    )
+    .map_err(|e| format!("{:?}", e))?;
+    let mut data = RMatrix::new_matrix(matrix.nrow(), matrix.ncol(), |row, col| {
+        let value = matrix.get(row, col);
+        if value.is_nan() {
+            f64::na()
+        } else {
+            value
+        }
+    })
+    .into_robj();
+    data.set_attrib(
+        "dimnames",
+        List::from_values([
+            Strings::from_values(matrix.rownames.iter().map(|v| v.as_str())),
+            Strings::from_values(matrix.colnames.iter().map(|v| v.as_str())),
+        ]),
+    )
+    .map_err(|e| format!("{:?}", e))?;
+    List::from_names_and_values(["series", "sample", "matrix", "coerced"], [
+        series.into_robj(),
+        sample.into_robj(),
+        data,
+        Robj::from(matrix.coerced as f64),
+    ])
+    .map_err(|e| format!("{:?}", e))
+}
+
+/// Parse an NCBI-generated RNA-seq table into a gene-by-GSM matrix, of
+/// integers if `integer` (raw counts), or else of doubles. Counts that are
+/// not integers within the range of R integers make the whole matrix double.
+#[extendr]
+fn read_rnaseq(path: &str, integer: bool) -> Result<Robj, String> {
+    let table = open_text(Path::new(path))
+        .and_then(parse_rnaseq)
+        .with_context(|| format!("Failed to parse {}", path))
+        .map_err(|e| format!("{:?}", e))?;
+    let integer = integer && {
+        let invalid = (0 .. table.ncol())
+            .flat_map(|col| (0 .. table.nrow()).map(move |row| (row, col)))
+            .find(|&(row, col)| {
+                let value = table.get(row, col);
+                !value.is_nan() && (value.fract() != 0.0 || value.abs() > i32::MAX as f64)
+            });
+        if let Some((row, col)) = invalid {
+            eprintln!(
+                "Warning: count {} of gene {} in {} is not an R integer, reading a double matrix",
+                table.get(row, col),
+                table.rownames[row],
+                table.colnames[col]
+            );
+        }
+        invalid.is_none()
+    };
+    let mut data = if integer {
+        RMatrix::new_matrix(table.nrow(), table.ncol(), |row, col| {
+            let value = table.get(row, col);
+            if value.is_nan() {
+                i32::na()
+            } else {
+                value as i32
+            }
+        })
+        .into_robj()
+    } else {
+        RMatrix::new_matrix(table.nrow(), table.ncol(), |row, col| {
+            let value = table.get(row, col);
+            if value.is_nan() {
+                f64::na()
+            } else {
+                value
+            }
+        })
+        .into_robj()
+    };
+    data.set_attrib(
+        "dimnames",
+        List::from_values([
+            Strings::from_values(table.rownames.iter().map(|v| v.as_str())),
+            Strings::from_values(table.colnames.iter().map(|v| v.as_str())),
+        ]),
+    )
+    .map_err(|e| format!("{:?}", e))?;
+    Ok(data)
+}
+
+extendr_module! {
+    mod matrix;
+    fn read_series_matrix;
+    fn read_rnaseq;
+}
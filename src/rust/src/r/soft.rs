@@ -0,0 +1,143 @@
+use std::path::Path;
+use std::result::Result;
+
+use anyhow::{anyhow, Context};
+use extendr_api::prelude::*;
+
+use super::helper::column_to_robj;
+use crate::parser::annot::{parse_annot, AnnotColumn};
+use crate::parser::miniml::MinimlReader;
+use crate::parser::open_text;
+use crate::parser::soft::{SoftEntity, SoftReader};
+
+/// Parse a SOFT file (optionally gzip-compressed) into a list of entities,
+/// each converted into R objects as soon as it is parsed.
+#[extendr]
+fn read_soft(path: &str, kinds: Robj, only_meta: bool) -> Result<List, String> {
+    let reader = open_text(Path::new(path)).map_err(|e| format!("{:?}", e))?;
+    let mut reader = SoftReader::new(reader);
+    if !kinds.is_null() {
+        reader.kinds(robj_to_kinds(&kinds).map_err(|e| format!("{:?}", e))?);
+    }
+    reader.only_meta(only_meta);
+    let entities = reader
+        .map(|entity| {
+            entity.and_then(|entity| entity_to_list(entity).map_err(|e| anyhow!("{:?}", e)))
+        })
+        .collect::<anyhow::Result<Vec<List>>>()
+        .with_context(|| format!("Failed to parse {}", path))
+        .map_err(|e| format!("{:?}", e))?;
+    Ok(List::from_values(entities))
+}
+
+/// Parse a MINiML file into a list of entities like `read_soft()`. Archives
+/// are extracted into `exdir`.
+#[extendr]
+fn read_miniml(path: &str, exdir: &str, kinds: Robj, only_meta: bool) -> Result<List, String> {
+    let mut reader = MinimlReader::new();
+    if !kinds.is_null() {
+        reader.kinds(robj_to_kinds(&kinds).map_err(|e| format!("{:?}", e))?);
+    }
+    reader.only_meta(only_meta);
+    let entities = reader
+        .read(Path::new(path), Path::new(exdir))
+        .with_context(|| format!("Failed to read {}", path))
+        .map_err(|e| format!("{:?}", e))?
+        .into_iter()
+        .map(entity_to_list)
+        .collect::<extendr_api::Result<Vec<List>>>()
+        .map_err(|e| format!("{:?}", e))?;
+    Ok(List::from_values(entities))
+}
+
+/// Parse a GPL annotation file into `list(meta, columns = list(name,
+/// description), table)`. Table columns are typed like those of SOFT files
+/// (e.g. `ID` and `Gene ID` are integers when all their values are), except
+/// Gene Ontology columns which are lists of character vectors.
+#[extendr]
+fn read_annot(path: &str) -> Result<List, String> {
+    let annot = open_text(Path::new(path))
+        .and_then(parse_annot)
+        .with_context(|| format!("Failed to parse {}", path))
+        .map_err(|e| format!("{:?}", e))?;
+    let meta = List::from_names_and_values(
+        annot.meta.iter().map(|(name, _)| name.as_str()),
+        annot
+            .meta
+            .iter()
+            .map(|(_, values)| Strings::from_values(values.iter().map(|v| v.as_str())).into_robj()),
+    )
+    .map_err(|e| format!("{:?}", e))?;
+    let columns = List::from_names_and_values(["name", "description"], [
+        Strings::from_values(annot.columns.iter().map(|(name, _)| name.as_str())).into_robj(),
+        Strings::from_values(annot.columns.iter().map(|(_, desc)| desc.as_str())).into_robj(),
+    ])
+    .map_err(|e| format!("{:?}", e))?;
+    let table = List::from_names_and_values(
+        annot.table.iter().map(|(name, _)| name.as_str()),
+        annot.table.iter().map(|(_, column)| match column {
+            AnnotColumn::Text(values) => column_to_robj(values),
+            AnnotColumn::Multi(values) => List::from_values(
+                values
+                    .iter()
+                    .map(|v| Strings::from_values(v.iter().map(|x| x.as_str()))),
+            )
+            .into_robj(),
+        }),
+    )
+    .map_err(|e| format!("{:?}", e))?;
+    List::from_names_and_values(["meta", "columns", "table"], [
+        meta.into_robj(),
+        columns.into_robj(),
+        table.into_robj(),
+    ])
+    .map_err(|e| format!("{:?}", e))
+}
+
+fn robj_to_kinds(kinds: &Robj) -> anyhow::Result<Vec<String>> {
+    Ok(kinds
+        .as_str_vector()
+        .ok_or_else(|| anyhow!("Expected a character vector"))
+        .with_context(|| "Invalid 'kinds'")?
+        .into_iter()
+        .map(|kind| kind.to_string())
+        .collect())
+}
+
+/// Convert an entity into
+/// `list(kind, accession, meta, columns = list(name, description), table)`.
+fn entity_to_list(entity: SoftEntity) -> extendr_api::Result<List> {
+    let meta = List::from_names_and_values(
+        entity.meta.iter().map(|(name, _)| name.as_str()),
+        entity
+            .meta
+            .iter()
+            .map(|(_, values)| Strings::from_values(values.iter().map(|v| v.as_str())).into_robj()),
+    )?;
+    let columns = List::from_names_and_values(["name", "description"], [
+        Strings::from_values(entity.columns.iter().map(|(name, _)| name.as_str())).into_robj(),
+        Strings::from_values(entity.columns.iter().map(|(_, desc)| desc.as_str())).into_robj(),
+    ])?;
+    let table = match entity.table {
+        Some(table) => List::from_names_and_values(
+            table.header.iter().map(|name| name.as_str()),
+            table.columns.iter().map(|column| column_to_robj(column)),
+        )?
+        .into_robj(),
+        None => Robj::from(()),
+    };
+    List::from_names_and_values(["kind", "accession", "meta", "columns", "table"], [
+        Robj::from(entity.kind),
+        Robj::from(entity.accession),
+        meta.into_robj(),
+        columns.into_robj(),
+        table,
+    ])
+}
+
+extendr_module! {
+    mod soft;
+    fn read_soft;
+    fn read_miniml;
+    fn read_annot;
+}
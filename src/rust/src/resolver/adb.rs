@@ -2,6 +2,7 @@ use std::fmt;
 use std::result::Result;
 use std::str::FromStr;
 
+use super::endpoints::GEOEndpoints;
 use super::error::GEOParseError;
 use super::identifier::{GEOIdentifier, GEOType};
 use super::GEOEntry;
@@ -280,14 +281,15 @@ impl GEOADBResolver {
 
 fn acc_url(accession: &str, scope: &GEOScope, amount: &GEOAmount, format: &GEOADBFormat) -> String {
     format!(
-        "https://www.ncbi.nlm.nih.gov/geo/query/acc.cgi?acc={}&targ={}&view={}&form={}",
-        accession, scope, amount, format
+        "{}?acc={}&targ={}&view={}&form={}",
+        GEOEndpoints::current().adb,
+        accession,
+        scope,
+        amount,
+        format
     )
 }
 
 fn gds_url(accession: &str) -> String {
-    format!(
-        "https://www.ncbi.nlm.nih.gov/sites/GDSbrowser?acc={}",
-        accession
-    )
+    format!("{}?acc={}", GEOEndpoints::current().gds, accession)
 }
@@ -0,0 +1,102 @@
+use std::sync::{Arc, OnceLock, RwLock};
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::Url;
+
+pub(crate) const DEFAULT_ADB_URL: &str = "https://www.ncbi.nlm.nih.gov/geo/query/acc.cgi";
+pub(crate) const DEFAULT_GDS_URL: &str = "https://www.ncbi.nlm.nih.gov/sites/GDSbrowser";
+pub(crate) const DEFAULT_FTP_HTTPS_URL: &str = "https://ftp.ncbi.nlm.nih.gov/geo";
+pub(crate) const DEFAULT_FTP_URL: &str = "ftp://ftp.ncbi.nlm.nih.gov/geo";
+pub(crate) const DEFAULT_DOWNLOAD_URL: &str = "https://www.ncbi.nlm.nih.gov/geo/download";
+pub(crate) const DEFAULT_SRA_URL: &str = "https://www.ncbi.nlm.nih.gov/sra";
+
+/// Base URLs of the GEO endpoints used by the resolvers, so that a mirror or
+/// a local stand-in server can replace NCBI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GEOEndpoints {
+    /// Accession Display Bar (`acc.cgi`), queried with `?acc=...`.
+    pub(crate) adb: String,
+    /// DataSet browser, queried with `?acc=...`.
+    pub(crate) gds: String,
+    /// Root of the GEO FTP site served over HTTPS.
+    pub(crate) ftp_https: String,
+    /// Root of the GEO FTP site served over FTP.
+    pub(crate) ftp: String,
+    /// GEO download page of the NCBI-generated RNA-seq tables, queried with
+    /// `?acc=...`.
+    pub(crate) download: String,
+    /// SRA search page, queried with `?term=...`.
+    pub(crate) sra: String,
+}
+
+impl Default for GEOEndpoints {
+    fn default() -> Self {
+        Self {
+            adb: DEFAULT_ADB_URL.to_string(),
+            gds: DEFAULT_GDS_URL.to_string(),
+            ftp_https: DEFAULT_FTP_HTTPS_URL.to_string(),
+            ftp: DEFAULT_FTP_URL.to_string(),
+            download: DEFAULT_DOWNLOAD_URL.to_string(),
+            sra: DEFAULT_SRA_URL.to_string(),
+        }
+    }
+}
+
+fn global() -> &'static RwLock<Arc<GEOEndpoints>> {
+    static ENDPOINTS: OnceLock<RwLock<Arc<GEOEndpoints>>> = OnceLock::new();
+    ENDPOINTS.get_or_init(|| RwLock::new(Arc::new(GEOEndpoints::default())))
+}
+
+impl GEOEndpoints {
+    /// Build endpoints from base URLs, checking their schemes: HTTP(S) for
+    /// all but `ftp`, which may also be FTP. Trailing slashes are removed.
+    pub(crate) fn new(
+        adb: &str,
+        gds: &str,
+        ftp_https: &str,
+        ftp: &str,
+        download: &str,
+        sra: &str,
+    ) -> Result<Self> {
+        Ok(Self {
+            adb: base_url(adb, &["http", "https"]).with_context(|| "Invalid 'adb' endpoint")?,
+            gds: base_url(gds, &["http", "https"]).with_context(|| "Invalid 'gds' endpoint")?,
+            ftp_https: base_url(ftp_https, &["http", "https"])
+                .with_context(|| "Invalid 'ftp_https' endpoint")?,
+            ftp: base_url(ftp, &["ftp", "http", "https"])
+                .with_context(|| "Invalid 'ftp' endpoint")?,
+            download: base_url(download, &["http", "https"])
+                .with_context(|| "Invalid 'download' endpoint")?,
+            sra: base_url(sra, &["http", "https"]).with_context(|| "Invalid 'sra' endpoint")?,
+        })
+    }
+
+    /// The endpoints currently used by the resolvers.
+    pub(crate) fn current() -> Arc<GEOEndpoints> {
+        global().read().unwrap().clone()
+    }
+
+    /// Replace the endpoints used by the resolvers.
+    pub(crate) fn set(endpoints: GEOEndpoints) {
+        let mut current = global().write().unwrap();
+        if **current != endpoints {
+            *current = Arc::new(endpoints);
+        }
+    }
+}
+
+fn base_url(url: &str, schemes: &[&str]) -> Result<String> {
+    let url = url.trim().trim_end_matches('/');
+    let parsed = Url::parse(url).with_context(|| format!("Not a valid URL: {}", url))?;
+    if !schemes.contains(&parsed.scheme()) {
+        return Err(anyhow!(
+            "Expected a URL with scheme {}, got '{}'",
+            schemes.join(" or "),
+            parsed.scheme()
+        ));
+    }
+    if parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(anyhow!("Expected a URL without query or fragment: {}", url));
+    }
+    Ok(url.to_string())
+}
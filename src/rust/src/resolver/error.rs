@@ -7,6 +7,25 @@ pub(crate) enum GEOParseError {
     #[error("Expected one starting with 'GDS', 'GPL', 'GSM', or 'GSE', and followed by digits.")]
     InvalidAccession,
 
+    #[error("Invalid token '{token}'")]
+    InvalidToken {
+        token: String,
+        #[source]
+        source: Box<GEOParseError>,
+    },
+
+    #[error("Expected two accessions of the same type joined by '-' or '..', such as 'GSM100-GSM199' or 'GSE1..GSE20'.")]
+    InvalidRange,
+
+    #[error("Expected a range in ascending order.")]
+    DescendingRange,
+
+    #[error("Expected a range of at most {max} accessions.")]
+    RangeTooLarge { max: u64 },
+
+    #[error("Expected a GEO, SRA, BioProject, or BioSample accession, such as 'GSE2', 'SRR390728', 'PRJNA257197', or 'SAMN02953559'.")]
+    UnknownAccession,
+
     #[error("Expected one of 'brief', 'quick', 'data', or 'full'.")]
     InvalidAmount,
 
@@ -31,11 +50,14 @@ pub(crate) enum GEOParseError {
     #[error("Expected one of 'soft', 'soft_full', 'miniml', 'matrix', 'annot', or 'suppl'.")]
     InvalidFTPFormat,
 
+    #[error("Expected one of 'rnaseq_counts', 'rnaseq_tpm', 'rnaseq_fpkm', or 'rnaseq_annot'.")]
+    InvalidRNASeqFormat,
+
     #[error("{gtype} never own {ftype} file.")]
     // Use `String` instead, because `GEOFile` is private.
     UnavailableFormat { gtype: GEOType, ftype: String },
 
-    #[error("Expected one of 'soft', 'soft_full', 'miniml', 'matrix', 'annot', 'suppl', 'text', 'xml', or 'html'.")]
+    #[error("Expected one of 'soft', 'soft_full', 'miniml', 'matrix', 'annot', 'suppl', 'text', 'xml', 'html', 'sra', 'rnaseq_counts', 'rnaseq_tpm', 'rnaseq_fpkm', or 'rnaseq_annot'.")]
     InvalidFormat,
 
     #[error("Expected one of 'none', 'brief', 'quick', 'data', 'full', 'soft', 'soft_full', 'miniml', 'matrix', 'annot', or 'suppl'.")]
@@ -0,0 +1,43 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use super::identifier::{Accession, Database};
+
+/// Find the accessions of `databases` in free text, such as the methods
+/// section of a paper or a list of URLs.
+///
+/// Texts are cut into runs of ASCII letters and digits, and each run is
+/// validated as an accession, so accessions are found in running text as well
+/// as in URLs and file names, where punctuation delimits them:
+/// ```text
+/// https://www.ncbi.nlm.nih.gov/geo/query/acc.cgi?acc=GSE2
+/// https://www.ncbi.nlm.nih.gov/sites/GDSbrowser?acc=GDS505
+/// https://ftp.ncbi.nlm.nih.gov/geo/series/GSE1nnn/GSE1000/soft/GSE1000_family.soft.gz
+/// GSE781-GPL96_series_matrix.txt.gz
+/// ```
+/// Directory stubs such as `GSE1nnn` are not accessions and are skipped.
+/// Accessions are upper-cased and returned once, in order of first
+/// appearance across `texts`.
+pub(crate) fn extract_accessions(texts: &[&str], databases: &[Database]) -> Vec<Accession> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for text in texts {
+        let words = text
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            // accessions start with a letter and end with a digit
+            .filter(|word| {
+                word.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && word.ends_with(|c: char| c.is_ascii_digit())
+            });
+        for word in words {
+            let Ok(accession) = Accession::from_str(word) else {
+                continue;
+            };
+            if databases.contains(&accession.database()) && seen.insert(accession.accession.clone())
+            {
+                out.push(accession);
+            }
+        }
+    }
+    out
+}
@@ -6,6 +6,7 @@ use std::sync::OnceLock;
 use anyhow::Context;
 use regex::Regex;
 
+use super::endpoints::GEOEndpoints;
 use super::error::GEOParseError;
 use super::identifier::{GEOIdentifier, GEOType};
 use super::GEOEntry;
@@ -136,13 +137,14 @@ impl GEOFTPResolver {
                 .with_context(|| "Failed to create regex")
                 .unwrap()
         });
+        let endpoints = GEOEndpoints::current();
         format!(
             "{}/{}/{}/{}/{}",
             // Construct the FTP/HTTPS download URL for the current GEO identifier and file type.
             if self.over_https {
-                "https://ftp.ncbi.nlm.nih.gov/geo"
+                &endpoints.ftp_https
             } else {
-                "ftp://ftp.ncbi.nlm.nih.gov/geo"
+                &endpoints.ftp
             },
             // GEO FTP server uses lowercase
             self.id.gtype.to_string().to_ascii_lowercase(),
@@ -206,7 +208,7 @@ impl GEOFTPResolver {
         if let Some(fname) = fname {
             GEOEntry::File {
                 url: format!("{}/{}", self.landing_page(), fname),
-                fname: fname,
+                fname,
             }
         } else {
             GEOEntry::Dir {
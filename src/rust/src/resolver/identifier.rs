@@ -1,6 +1,10 @@
 use std::fmt;
 use std::result::Result;
 use std::str::FromStr;
+use std::sync::OnceLock;
+
+use anyhow::Context;
+use regex::Regex;
 
 use super::error::GEOParseError;
 
@@ -44,7 +48,7 @@ impl FromStr for GEOType {
 impl TryFrom<&str> for GEOType {
     type Error = GEOParseError;
     fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
-        Self::from_str(value.as_ref())
+        Self::from_str(value)
     }
 }
 
@@ -72,12 +76,10 @@ impl FromStr for GEOIdentifier {
             return Err(GEOParseError::InvalidAccession);
         };
 
-        // SAFETY: `accession` must be >= 4 characters if it passed the prefix check above.
+        // SAFETY: `accession` must be >= 3 characters if it passed the prefix check above.
         // We slice from index 3 (after prefix) to get the numeric part, which must consist of digits only.
-        if !unsafe { accession.get_unchecked(3 ..) }
-            .chars()
-            .all(|c| c.is_ascii_digit())
-        {
+        let digits = unsafe { accession.get_unchecked(3 ..) };
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
             return Err(GEOParseError::InvalidAccession);
         }
 
@@ -88,6 +90,245 @@ impl FromStr for GEOIdentifier {
 impl TryFrom<&str> for GEOIdentifier {
     type Error = GEOParseError;
     fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
-        Self::from_str(value.as_ref())
+        Self::from_str(value)
+    }
+}
+
+/// Largest number of accessions a single range may expand to.
+const MAX_RANGE_LEN: u64 = 100_000;
+
+/// Expand `expr` into GEO accessions.
+///
+/// `expr` is a list of accessions and ranges separated by commas or
+/// whitespace. A range is two accessions of the same type joined by `-` or
+/// `..`, both ends included, e.g. `"GSE1, GSE5..GSE7 GSM100-GSM102"`.
+/// Errors point at the offending token.
+pub(crate) fn expand_accessions(expr: &str) -> Result<Vec<String>, GEOParseError> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let regex = RE.get_or_init(|| {
+        Regex::new(r"\s*(\.\.|-)\s*")
+            .with_context(|| "Failed to create regex")
+            .unwrap()
+    });
+    // Allow spaces around the range operators, as in `GSE1 .. GSE20`.
+    let expr = regex.replace_all(expr, "$1");
+    let mut out = Vec::new();
+    for token in expr
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+    {
+        expand_token(token)
+            .map(|ids| out.extend(ids))
+            .map_err(|e| GEOParseError::InvalidToken {
+                token: token.to_string(),
+                source: Box::new(e),
+            })?;
+    }
+    if out.is_empty() {
+        return Err(GEOParseError::InvalidAccession);
+    }
+    Ok(out)
+}
+
+fn expand_token(token: &str) -> Result<Vec<String>, GEOParseError> {
+    let range = token.split_once("..").or_else(|| token.split_once('-'));
+    let Some((start, end)) = range else {
+        return GEOIdentifier::from_str(token).map(|id| vec![id.accession]);
+    };
+    let start = GEOIdentifier::from_str(start)?;
+    let end = GEOIdentifier::from_str(end)?;
+    let prefix = start.gtype.abbre();
+    if prefix != end.gtype.abbre() {
+        return Err(GEOParseError::InvalidRange);
+    }
+    // Digits were checked by `GEOIdentifier`, only overflow may fail here.
+    let from: u64 = start.accession[3 ..]
+        .parse()
+        .map_err(|_| GEOParseError::InvalidRange)?;
+    let to: u64 = end.accession[3 ..]
+        .parse()
+        .map_err(|_| GEOParseError::InvalidRange)?;
+    if from > to {
+        return Err(GEOParseError::DescendingRange);
+    }
+    if to - from >= MAX_RANGE_LEN {
+        return Err(GEOParseError::RangeTooLarge { max: MAX_RANGE_LEN });
+    }
+    Ok((from ..= to).map(|n| format!("{}{}", prefix, n)).collect())
+}
+
+/// Databases whose accessions are recognised by [`Accession`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Database {
+    GEO,
+    SRA,
+    BioProject,
+    BioSample,
+}
+
+impl fmt::Display for Database {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            Database::GEO => "GEO",
+            Database::SRA => "SRA",
+            Database::BioProject => "BioProject",
+            Database::BioSample => "BioSample",
+        })
+    }
+}
+
+/// Record types of the Sequence Read Archive, told apart by the third letter
+/// of the accession (e.g. `SRR`, `ERR` and `DRR` are all runs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SRAType {
+    Submission,
+    Study,
+    Sample,
+    Experiment,
+    Run,
+}
+
+impl SRAType {
+    pub(crate) fn abbre(&self) -> &'static str {
+        match self {
+            SRAType::Submission => "SRA",
+            SRAType::Study => "SRP",
+            SRAType::Sample => "SRS",
+            SRAType::Experiment => "SRX",
+            SRAType::Run => "SRR",
+        }
+    }
+}
+
+impl fmt::Display for SRAType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            SRAType::Submission => "Submission",
+            SRAType::Study => "Study",
+            SRAType::Sample => "Sample",
+            SRAType::Experiment => "Experiment",
+            SRAType::Run => "Run",
+        })
+    }
+}
+
+/// Type of an accession of any supported database.
+#[derive(Debug, Clone)]
+pub(crate) enum AccessionType {
+    GEO(GEOType),
+    SRA(SRAType),
+    /// BioProject, with its prefix (`PRJNA`, `PRJEB` or `PRJDB`).
+    BioProject(&'static str),
+    /// BioSample, with its prefix (`SAMN`, `SAMEA`, `SAME` or `SAMD`).
+    BioSample(&'static str),
+}
+
+impl AccessionType {
+    pub(crate) fn database(&self) -> Database {
+        match self {
+            AccessionType::GEO(_) => Database::GEO,
+            AccessionType::SRA(_) => Database::SRA,
+            AccessionType::BioProject(_) => Database::BioProject,
+            AccessionType::BioSample(_) => Database::BioSample,
+        }
+    }
+
+    /// Canonical prefix of the type. SRA records use the NCBI prefix of their
+    /// type for all INSDC mirrors (e.g. `SRR` for `ERR`), while BioProject and
+    /// BioSample records keep the prefix of the archive they come from.
+    pub(crate) fn abbre(&self) -> &'static str {
+        match self {
+            AccessionType::GEO(gtype) => gtype.abbre(),
+            AccessionType::SRA(stype) => stype.abbre(),
+            AccessionType::BioProject(prefix) | AccessionType::BioSample(prefix) => prefix,
+        }
+    }
+}
+
+impl fmt::Display for AccessionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessionType::GEO(gtype) => fmt::Display::fmt(gtype, f),
+            AccessionType::SRA(stype) => fmt::Display::fmt(stype, f),
+            AccessionType::BioProject(_) => write!(f, "Project"),
+            AccessionType::BioSample(_) => write!(f, "BioSample"),
+        }
+    }
+}
+
+/// An accession of GEO, SRA, BioProject or BioSample.
+///
+/// | Database   | Prefixes                        | Example        |
+/// | :--------- | :------------------------------ | :------------- |
+/// | GEO        | `GDS`, `GPL`, `GSM`, `GSE`      | `GSE2`         |
+/// | SRA        | `[SED]R[APSXR]`, 6+ digits      | `SRR390728`    |
+/// | BioProject | `PRJNA`, `PRJEB`, `PRJDB`       | `PRJNA257197`  |
+/// | BioSample  | `SAMN`, `SAMEA`, `SAME`, `SAMD` | `SAMN02953559` |
+#[derive(Debug, Clone)]
+pub(crate) struct Accession {
+    pub(crate) accession: String,
+    pub(crate) atype: AccessionType,
+}
+
+impl Accession {
+    pub(crate) fn database(&self) -> Database {
+        self.atype.database()
+    }
+}
+
+impl FromStr for Accession {
+    type Err = GEOParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(id) = GEOIdentifier::from_str(s) {
+            return Ok(Self {
+                accession: id.accession,
+                atype: AccessionType::GEO(id.gtype),
+            });
+        }
+        static RE: OnceLock<Regex> = OnceLock::new();
+        let re = RE.get_or_init(|| {
+            Regex::new(concat!(
+                r"^(?:[SED]R(?<sra>[APSXR])\d{6,}",
+                r"|(?<bioproject>PRJ(?:NA|EB|DB))\d+",
+                r"|(?<biosample>SAM(?:N|EA|E|D))\d+)$"
+            ))
+            .with_context(|| "Failed to create regex")
+            .unwrap()
+        });
+        let accession = s.to_ascii_uppercase();
+        let caps = re
+            .captures(&accession)
+            .ok_or(GEOParseError::UnknownAccession)?;
+        let atype = if let Some(sra) = caps.name("sra") {
+            AccessionType::SRA(match sra.as_str() {
+                "A" => SRAType::Submission,
+                "P" => SRAType::Study,
+                "S" => SRAType::Sample,
+                "X" => SRAType::Experiment,
+                _ => SRAType::Run,
+            })
+        } else if let Some(prefix) = caps.name("bioproject") {
+            AccessionType::BioProject(match prefix.as_str() {
+                "PRJEB" => "PRJEB",
+                "PRJDB" => "PRJDB",
+                _ => "PRJNA",
+            })
+        } else {
+            let prefix = caps.name("biosample").map_or("", |m| m.as_str());
+            AccessionType::BioSample(match prefix {
+                "SAMEA" => "SAMEA",
+                "SAME" => "SAME",
+                "SAMD" => "SAMD",
+                _ => "SAMN",
+            })
+        };
+        Ok(Self { accession, atype })
+    }
+}
+
+impl TryFrom<&str> for Accession {
+    type Error = GEOParseError;
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        Self::from_str(value)
     }
 }
@@ -2,14 +2,28 @@ use anyhow::{Context, Result};
 
 // Accession Display Bar
 mod adb;
+// base URLs of the GEO endpoints
+mod endpoints;
 mod error;
+// accessions found in free text
+mod extract;
 // FTP site
 mod ftp;
 mod identifier;
+// RNA-seq tables generated by NCBI
+mod rnaseq;
+// SRA runs of GEO Series and Samples
+mod sra;
+// GEO URLs parsed back into resolver options
+mod url;
 
+pub(crate) use endpoints::GEOEndpoints;
 pub(crate) use error::GEOParseError;
+pub(crate) use extract::extract_accessions;
 use identifier::GEOIdentifier;
-pub(crate) use identifier::GEOType;
+pub(crate) use identifier::{expand_accessions, Accession, Database, GEOType};
+pub(crate) use rnaseq::{rnaseq_file_url, GEORNASeqFormat};
+pub(crate) use url::{parse_geo_url, GEOUrlParts};
 
 /// Resolver for GEO (Gene Expression Omnibus) resources.
 ///
@@ -17,9 +31,11 @@ pub(crate) use identifier::GEOType;
 /// determine whether the target resource is a directory or a file,
 /// depending on the type of identifier and resolution strategy.
 ///
-/// Currently, two resolver backends are supported:
+/// Currently, four resolver backends are supported:
 /// - [`FTP`](GEOResolver::FTP): For direct FTP/HTTPS file retrieval from GEO FTP servers.
 /// - [`ADB`](GEOResolver::ADB): For file retrieval from Accession Display Bar of GEO database.
+/// - [`SRA`](GEOResolver::SRA): For the sequencing runs of Series and Samples in SRA.
+/// - [`RNASeq`](GEOResolver::RNASeq): For RNA-seq count tables generated by NCBI for Series.
 pub(crate) struct GEOResolver(GEOResolverInner);
 
 enum GEOResolverInner {
@@ -28,6 +44,12 @@ enum GEOResolverInner {
 
     /// Resolver for FTP/HTTPS-based requests.
     FTP(ftp::GEOFTPResolver),
+
+    /// Resolver for the SRA runs of Series and Samples.
+    SRA(sra::GEOSRAResolver),
+
+    /// Resolver for the NCBI-generated RNA-seq tables of Series.
+    RNASeq(rnaseq::GEORNASeqResolver),
 }
 
 /// The terminal entry of a GEO URL, which may be a file or directory.
@@ -51,6 +73,8 @@ impl GEOResolver {
     /// - `format`: Optional "file/amount type":
     ///     - **FTP**: "soft", "soft_full", "miniml", "matrix", "annot", or "suppl"
     ///     - **ADB** (Accession Display Bar): "text", "xml", "html"
+    ///     - **SRA**: "sra"
+    ///     - **RNASeq**: "rnaseq_counts", "rnaseq_tpm", "rnaseq_fpkm", "rnaseq_annot"
     /// - `amount`: "none", "brief", "quick", "data", "full" (ADB-only option).
     /// - `scope`: "none", "self", "gsm", "gpl", "gse", or "all" (ADB-only option).
     /// - `over_https`: FTP-only option (default: true).
@@ -58,6 +82,8 @@ impl GEOResolver {
     /// # Returns
     /// - `Ok(GEOResolver::ADB(..))` if an ADB `format` is selected or defaulted.
     /// - `Ok(GEOResolver::FTP(..))` if an FTP `format` is selected.
+    /// - `Ok(GEOResolver::SRA(..))` if `format` is "sra".
+    /// - `Ok(GEOResolver::RNASeq(..))` if an RNA-seq table `format` is selected.
     /// - `Err(String)` if `format` or other arguments are invalid or incompatible.
     pub(crate) fn new(
         accession: &str,
@@ -83,7 +109,8 @@ impl GEOResolver {
                 let adb_format = format
                     .try_into()
                     .with_context(|| format!("Invalid 'format': {}", format))?;
-                solver.set_format(adb_format)
+                solver
+                    .set_format(adb_format)
                     .with_context(|| format!("Invalid 'format': {}", format))?;
 
                 // Parse optional format (default handled by resolver if None)
@@ -96,7 +123,8 @@ impl GEOResolver {
                                 .with_context(|| format!("Invalid 'amount': {}", amount))?,
                         ),
                     };
-                    solver.set_amount(value)
+                    solver
+                        .set_amount(value)
                         .with_context(|| format!("Invalid 'amount': {}", amount))?;
                 }
 
@@ -110,18 +138,19 @@ impl GEOResolver {
                                 .with_context(|| format!("Invalid 'scope': {}", scope))?,
                         ),
                     };
-                    solver.set_scope(value)
+                    solver
+                        .set_scope(value)
                         .with_context(|| format!("Invalid 'scope': {}", scope))?;
                 }
 
                 // over_https has no effect for ADB
-                if let Some(_) = over_https {
+                if over_https.is_some() {
                     eprintln!(
                         "Warning: 'over_https' will be ignored for {} 'format'",
                         format
                     )
                 }
-                return Ok(GEOResolver(GEOResolverInner::ADB(solver)));
+                Ok(GEOResolver(GEOResolverInner::ADB(solver)))
             }
 
             // ---------- FTP endpoint ----------
@@ -136,7 +165,8 @@ impl GEOResolver {
                 let ftp_format = format
                     .try_into()
                     .with_context(|| format!("Invalid 'format': {}", format))?;
-                solver.set_format(ftp_format)
+                solver
+                    .set_format(ftp_format)
                     .with_context(|| format!("Invalid 'format': {}", format))?;
 
                 // Apply HTTPS preference (default true if not set)
@@ -145,26 +175,90 @@ impl GEOResolver {
                 }
 
                 // Warn about ignored parameters
-                if let Some(_) = amount {
+                if amount.is_some() {
+                    eprintln!("Warning: 'amount' will be ignored for {} 'format'", format)
+                }
+                if scope.is_some() {
+                    eprintln!("Warning: 'scope' will be ignored for {} 'format'", format)
+                }
+                Ok(GEOResolver(GEOResolverInner::FTP(solver)))
+            }
+
+            // ---------- SRA runs ----------
+            // format: "sra"
+            // - Only Series and Samples are linked to SRA.
+            // - amount, scope, and over_https are always ignored.
+            "sra" => {
+                let solver = sra::GEOSRAResolver::new(id)
+                    .with_context(|| format!("Invalid 'format': {}", format))?;
+                if amount.is_some() {
                     eprintln!("Warning: 'amount' will be ignored for {} 'format'", format)
                 }
-                if let Some(_) = scope {
+                if scope.is_some() {
                     eprintln!("Warning: 'scope' will be ignored for {} 'format'", format)
                 }
-                return Ok(GEOResolver(GEOResolverInner::FTP(solver)));
+                if over_https.is_some() {
+                    eprintln!(
+                        "Warning: 'over_https' will be ignored for {} 'format'",
+                        format
+                    )
+                }
+                Ok(GEOResolver(GEOResolverInner::SRA(solver)))
+            }
+
+            // ---------- NCBI-generated RNA-seq tables ----------
+            // format: "rnaseq_counts" | "rnaseq_tpm" | "rnaseq_fpkm" | "rnaseq_annot"
+            // - Only Series have such tables.
+            // - amount, scope, and over_https are always ignored.
+            "rnaseq_counts" | "rnaseq_tpm" | "rnaseq_fpkm" | "rnaseq_annot" => {
+                let rnaseq_format = format
+                    .try_into()
+                    .with_context(|| format!("Invalid 'format': {}", format))?;
+                let solver = rnaseq::GEORNASeqResolver::new(id, rnaseq_format)
+                    .with_context(|| format!("Invalid 'format': {}", format))?;
+                if amount.is_some() {
+                    eprintln!("Warning: 'amount' will be ignored for {} 'format'", format)
+                }
+                if scope.is_some() {
+                    eprintln!("Warning: 'scope' will be ignored for {} 'format'", format)
+                }
+                if over_https.is_some() {
+                    eprintln!(
+                        "Warning: 'over_https' will be ignored for {} 'format'",
+                        format
+                    )
+                }
+                Ok(GEOResolver(GEOResolverInner::RNASeq(solver)))
             }
 
             // ---------- Invalid famount ----------
             // If famount is not in ACC or FTP categories, return error.
             _ => {
-                return Err(GEOParseError::InvalidFormat).with_context(|| {
+                Err(GEOParseError::InvalidFormat).with_context(|| {
                     // Safe unwrap (famount is Some in this branch)
                     format!("Invalid 'format': {}", format)
-                });
+                })
             }
         }
     }
 
+    /// Build a GEOResolver from a GEO URL, the inverse of [`url`](Self::url).
+    ///
+    /// The options recovered by [`parse_geo_url`] are validated as in
+    /// [`new`](Self::new), so stored URLs can be re-targeted by building a
+    /// new resolver from the same accession with other options.
+    pub(crate) fn from_url(url: &str) -> Result<Self> {
+        let parts = parse_geo_url(url).with_context(|| format!("Invalid 'url': {}", url))?;
+        Self::new(
+            &parts.accession,
+            &parts.format,
+            parts.amount.as_deref(),
+            parts.scope.as_deref(),
+            parts.over_https,
+        )
+        .with_context(|| format!("Invalid 'url': {}", url))
+    }
+
     /// Returns the GEO accession string (e.g., "GSE12345" or "GSM67890")
     /// associated with this resolver.
     #[allow(dead_code)]
@@ -172,6 +266,8 @@ impl GEOResolver {
         match &self.0 {
             GEOResolverInner::ADB(resolver) => resolver.accession(),
             GEOResolverInner::FTP(resolver) => resolver.accession(),
+            GEOResolverInner::SRA(resolver) => resolver.accession(),
+            GEOResolverInner::RNASeq(resolver) => resolver.accession(),
         }
     }
 
@@ -182,6 +278,8 @@ impl GEOResolver {
         match &self.0 {
             GEOResolverInner::ADB(resolver) => resolver.gtype(),
             GEOResolverInner::FTP(resolver) => resolver.gtype(),
+            GEOResolverInner::SRA(resolver) => resolver.gtype(),
+            GEOResolverInner::RNASeq(resolver) => resolver.gtype(),
         }
     }
 
@@ -193,6 +291,8 @@ impl GEOResolver {
         match &self.0 {
             GEOResolverInner::ADB(resolver) => resolver.landing_page(),
             GEOResolverInner::FTP(resolver) => resolver.landing_page(),
+            GEOResolverInner::SRA(resolver) => resolver.landing_page(),
+            GEOResolverInner::RNASeq(resolver) => resolver.landing_page(),
         }
     }
 
@@ -205,6 +305,8 @@ impl GEOResolver {
         match &self.0 {
             GEOResolverInner::ADB(resolver) => resolver.url(),
             GEOResolverInner::FTP(resolver) => resolver.url(),
+            GEOResolverInner::SRA(resolver) => resolver.url(),
+            GEOResolverInner::RNASeq(resolver) => resolver.url(),
         }
     }
 
@@ -212,6 +314,9 @@ impl GEOResolver {
     ///
     /// - For `ADB` resolvers, the entry depends on the requested format/scope.
     /// - For `FTP` resolvers, the entry is always known (`File` or `Dir`).
+    /// - For `SRA` resolvers, there is no entry.
+    /// - For `RNASeq` resolvers, the entry is the download page listing the
+    ///   tables of the Series (`Dir`).
     ///
     /// Returns [`Some(GEOEntry)`] if the resolver produces a file or directory,
     /// or [`None`] if the resolver does not correspond to any concrete entry.
@@ -219,6 +324,16 @@ impl GEOResolver {
         match &self.0 {
             GEOResolverInner::ADB(resolver) => resolver.entry(),
             GEOResolverInner::FTP(resolver) => Some(resolver.entry()),
+            GEOResolverInner::SRA(resolver) => resolver.entry(),
+            GEOResolverInner::RNASeq(resolver) => Some(resolver.entry()),
+        }
+    }
+
+    /// Return the RNA-seq table requested, for `RNASeq` resolvers only.
+    pub(crate) fn rnaseq_format(&self) -> Option<GEORNASeqFormat> {
+        match &self.0 {
+            GEOResolverInner::RNASeq(resolver) => Some(resolver.format()),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,142 @@
+use std::fmt;
+use std::result::Result;
+use std::str::FromStr;
+
+use super::endpoints::GEOEndpoints;
+use super::error::GEOParseError;
+use super::identifier::{GEOIdentifier, GEOType};
+use super::GEOEntry;
+
+/// Tables computed by NCBI from the SRA runs of human and mouse RNA-seq
+/// Series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GEORNASeqFormat {
+    /// Raw counts (`GSEnnn_raw_counts_GRCh38.p13_NCBI.tsv.gz`).
+    Counts,
+    /// TPM normalized counts (`GSEnnn_norm_counts_TPM_GRCh38.p13_NCBI.tsv.gz`).
+    Tpm,
+    /// FPKM normalized counts (`GSEnnn_norm_counts_FPKM_GRCh38.p13_NCBI.tsv.gz`).
+    Fpkm,
+    /// Gene annotation shared by all Series (`Human.GRCh38.p13.annot.tsv.gz`).
+    Annot,
+}
+
+impl GEORNASeqFormat {
+    /// Whether `fname`, a file listed on the download page of `accession`,
+    /// holds this table.
+    pub(crate) fn matches(&self, accession: &str, fname: &str) -> bool {
+        if !fname.ends_with(".tsv.gz") {
+            return false;
+        }
+        let table = match fname
+            .strip_prefix(accession)
+            .and_then(|name| name.strip_prefix('_'))
+        {
+            Some(table) => table,
+            None => return *self == GEORNASeqFormat::Annot && fname.ends_with(".annot.tsv.gz"),
+        };
+        match self {
+            GEORNASeqFormat::Counts => table.starts_with("raw_counts_"),
+            GEORNASeqFormat::Tpm => table.starts_with("norm_counts_TPM_"),
+            GEORNASeqFormat::Fpkm => table.starts_with("norm_counts_FPKM_"),
+            GEORNASeqFormat::Annot => false,
+        }
+    }
+}
+
+impl fmt::Display for GEORNASeqFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            GEORNASeqFormat::Counts => "rnaseq_counts",
+            GEORNASeqFormat::Tpm => "rnaseq_tpm",
+            GEORNASeqFormat::Fpkm => "rnaseq_fpkm",
+            GEORNASeqFormat::Annot => "rnaseq_annot",
+        })
+    }
+}
+
+impl FromStr for GEORNASeqFormat {
+    type Err = GEOParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rnaseq_counts" => Ok(GEORNASeqFormat::Counts),
+            "rnaseq_tpm" => Ok(GEORNASeqFormat::Tpm),
+            "rnaseq_fpkm" => Ok(GEORNASeqFormat::Fpkm),
+            "rnaseq_annot" => Ok(GEORNASeqFormat::Annot),
+            _ => Err(GEOParseError::InvalidRNASeqFormat),
+        }
+    }
+}
+
+impl TryFrom<&str> for GEORNASeqFormat {
+    type Error = GEOParseError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from_str(value)
+    }
+}
+
+/// Resolver for the RNA-seq tables NCBI generates for Series.
+///
+/// File names carry the genome build, which depends on the organism, so the
+/// entry is the download page of the Series, listing the tables available
+/// (if any). Files are then fetched with [`rnaseq_file_url`].
+pub(super) struct GEORNASeqResolver {
+    id: GEOIdentifier,
+    format: GEORNASeqFormat,
+}
+
+impl GEORNASeqResolver {
+    pub(super) fn new(id: GEOIdentifier, format: GEORNASeqFormat) -> Result<Self, GEOParseError> {
+        match id.gtype {
+            GEOType::Series => Ok(Self { id, format }),
+            _ => Err(GEOParseError::UnavailableFormat {
+                gtype: id.gtype.clone(),
+                ftype: format.to_string(),
+            }),
+        }
+    }
+
+    pub(super) fn accession(&self) -> &str {
+        self.id.accession.as_str()
+    }
+
+    pub(super) fn gtype(&self) -> &GEOType {
+        &self.id.gtype
+    }
+
+    pub(super) fn format(&self) -> GEORNASeqFormat {
+        self.format
+    }
+
+    pub(super) fn landing_page(&self) -> String {
+        format!(
+            "{}/?acc={}",
+            GEOEndpoints::current().download,
+            self.id.accession
+        )
+    }
+
+    pub(super) fn url(&self) -> String {
+        self.landing_page()
+    }
+
+    pub(super) fn entry(&self) -> GEOEntry {
+        GEOEntry::Dir { url: self.url() }
+    }
+}
+
+/// Download URL of the NCBI-generated RNA-seq file `fname` of `accession`.
+///
+/// Annotation tables are shared by all Series, but `acc` is still kept in the
+/// query so that the URL can be parsed back with [`parse_geo_url`].
+///
+/// [`parse_geo_url`]: super::parse_geo_url
+pub(crate) fn rnaseq_file_url(accession: &str, fname: &str) -> String {
+    format!(
+        "{}/?type=rnaseq_counts&acc={}&format=file&file={}",
+        GEOEndpoints::current().download,
+        accession,
+        fname
+    )
+}
@@ -0,0 +1,47 @@
+use std::result::Result;
+
+use super::endpoints::GEOEndpoints;
+use super::error::GEOParseError;
+use super::identifier::{GEOIdentifier, GEOType};
+use super::GEOEntry;
+
+/// Resolver of the SRA records of a GEO Series or Sample.
+///
+/// Runs are not files of GEO; they are looked up through the NCBI
+/// E-utilities, so this resolver never gives a downloadable entry.
+pub(super) struct GEOSRAResolver {
+    id: GEOIdentifier,
+}
+
+impl GEOSRAResolver {
+    pub(super) fn new(id: GEOIdentifier) -> Result<Self, GEOParseError> {
+        match id.gtype {
+            GEOType::Series | GEOType::Samples => Ok(Self { id }),
+            _ => Err(GEOParseError::UnavailableFormat {
+                gtype: id.gtype.clone(),
+                ftype: "sra".to_string(),
+            }),
+        }
+    }
+
+    pub(super) fn accession(&self) -> &str {
+        self.id.accession.as_str()
+    }
+
+    pub(super) fn gtype(&self) -> &GEOType {
+        &self.id.gtype
+    }
+
+    pub(super) fn landing_page(&self) -> String {
+        format!("{}?acc={}", GEOEndpoints::current().adb, self.id.accession)
+    }
+
+    /// SRA search page of the experiments submitted with this accession.
+    pub(super) fn url(&self) -> String {
+        format!("{}?term={}", GEOEndpoints::current().sra, self.id.accession)
+    }
+
+    pub(super) fn entry(&self) -> Option<GEOEntry> {
+        None
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::Url;
+
+use super::endpoints::GEOEndpoints;
+use super::rnaseq::GEORNASeqFormat;
+
+/// The options of [`GEOResolver::new`](super::GEOResolver::new) recovered from
+/// a GEO URL.
+pub(crate) struct GEOUrlParts {
+    pub(crate) accession: String,
+    pub(crate) format: String,
+    pub(crate) amount: Option<String>,
+    pub(crate) scope: Option<String>,
+    pub(crate) over_https: Option<bool>,
+}
+
+/// Parse a GEO URL back into resolver options.
+///
+/// Supported URLs are:
+/// - Accession Display Bar pages
+///   (`https://www.ncbi.nlm.nih.gov/geo/query/acc.cgi?acc=GSE2&targ=self&view=brief&form=text`),
+///   where `targ`, `view` and `form` give the scope, amount and format,
+///   defaulting to those of the GEO website.
+/// - DataSet browser pages (`https://www.ncbi.nlm.nih.gov/sites/GDSbrowser?acc=GDS505`).
+/// - Files and directories of the GEO FTP site, over HTTPS or FTP
+///   (`https://ftp.ncbi.nlm.nih.gov/geo/series/GSE1nnn/GSE1000/soft/GSE1000_family.soft.gz`),
+///   where the sub-directory gives the format.
+/// - SRA searches (`https://www.ncbi.nlm.nih.gov/sra?term=GSE2`).
+/// - RNA-seq tables of the GEO download page
+///   (`https://www.ncbi.nlm.nih.gov/geo/download/?type=rnaseq_counts&acc=GSE164073&format=file&file=...`).
+///
+/// Pages and files of the configured [`GEOEndpoints`] are recognized as well,
+/// so URLs built against a mirror parse back.
+pub(crate) fn parse_geo_url(url: &str) -> Result<GEOUrlParts> {
+    let url = url.trim();
+    let parsed = Url::parse(url).with_context(|| "Not a valid URL")?;
+    let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+    let segments: Vec<&str> = parsed
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    let query = |name: &str| {
+        parsed
+            .query_pairs()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.into_owned())
+            .filter(|value| !value.is_empty())
+    };
+    let parts = |accession: String, format: &str| GEOUrlParts {
+        accession,
+        format: format.to_string(),
+        amount: None,
+        scope: None,
+        over_https: None,
+    };
+    let accession = || query("acc").ok_or_else(|| anyhow!("Missing 'acc' in the URL query"));
+    let adb = || -> Result<GEOUrlParts> {
+        let accession = accession()?;
+        let dataset = accession.to_ascii_uppercase().starts_with("GDS");
+        Ok(GEOUrlParts {
+            format: query("form")
+                .unwrap_or_else(|| "html".to_string())
+                .to_ascii_lowercase(),
+            // DataSets have neither scope nor amount.
+            amount: query("view")
+                .map(|value| value.to_ascii_lowercase())
+                .or_else(|| (!dataset).then(|| "quick".to_string())),
+            scope: query("targ")
+                .map(|value| value.to_ascii_lowercase())
+                .or_else(|| (!dataset).then(|| "self".to_string())),
+            over_https: None,
+            accession,
+        })
+    };
+    let sra = || -> Result<GEOUrlParts> {
+        let term = query("term").ok_or_else(|| anyhow!("Missing 'term' in the URL query"))?;
+        Ok(parts(term, "sra"))
+    };
+    let rnaseq = || -> Result<GEOUrlParts> {
+        let fname = query("file").ok_or_else(|| anyhow!("Missing 'file' in the URL query"))?;
+        // The shared annotation tables are also served without `acc`,
+        // which leaves nothing to re-target.
+        let accession = match query("acc") {
+            Some(accession) => accession,
+            None if fname.ends_with(".annot.tsv.gz") => {
+                return Err(anyhow!(
+                    "Missing 'acc' in the URL query: {} is shared by all Series",
+                    fname
+                ))
+            }
+            None => accession()?,
+        };
+        let format = [
+            GEORNASeqFormat::Counts,
+            GEORNASeqFormat::Tpm,
+            GEORNASeqFormat::Fpkm,
+            GEORNASeqFormat::Annot,
+        ]
+        .into_iter()
+        .find(|format| format.matches(&accession.to_ascii_uppercase(), &fname))
+        .ok_or_else(|| anyhow!("Unknown RNA-seq table: {}", fname))?;
+        Ok(parts(accession, &format.to_string()))
+    };
+
+    // URLs of the configured endpoints, which may be mirrors of NCBI
+    let endpoints = GEOEndpoints::current();
+    let base = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('/');
+    if base == endpoints.adb {
+        return adb();
+    }
+    if base == endpoints.gds {
+        return Ok(parts(accession()?, "html"));
+    }
+    if base == endpoints.download {
+        return rnaseq();
+    }
+    if base == endpoints.sra {
+        return sra();
+    }
+    for (root, over_https) in [(&endpoints.ftp_https, true), (&endpoints.ftp, false)] {
+        if let Some(path) = base.strip_prefix(root.as_str()) {
+            if path.is_empty() || path.starts_with('/') {
+                let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+                return parse_ftp_path(&segments, over_https);
+            }
+        }
+    }
+
+    if host == "ftp.ncbi.nlm.nih.gov" {
+        return match segments.split_first() {
+            Some((&"geo", segments)) => parse_ftp_path(segments, parsed.scheme() != "ftp"),
+            _ => Err(anyhow!("Expected a path of the GEO FTP site")),
+        };
+    }
+    if host != "www.ncbi.nlm.nih.gov" {
+        return Err(anyhow!(
+            "Expected a URL of www.ncbi.nlm.nih.gov, ftp.ncbi.nlm.nih.gov or a configured endpoint"
+        ));
+    }
+    match segments.as_slice() {
+        ["geo", "query", "acc.cgi"] => adb(),
+        ["sites", "GDSbrowser"] | ["geo", "gds", "GDSbrowser"] => Ok(parts(accession()?, "html")),
+        ["sra"] => sra(),
+        ["geo", "download"] => rnaseq(),
+        _ => Err(anyhow!("Unknown GEO page: {}", parsed.path())),
+    }
+}
+
+/// Parse the path of the GEO FTP site below its root, laid out as
+/// `{datasets,series,platforms,samples}/{stub}/{accession}/{format}/{file}`.
+fn parse_ftp_path(segments: &[&str], over_https: bool) -> Result<GEOUrlParts> {
+    let (gtype, accession, rest) = match segments {
+        [gtype, _, accession, rest @ ..] => (*gtype, *accession, rest),
+        _ => return Err(anyhow!("Expected a path of the GEO FTP site")),
+    };
+    let prefix = match gtype {
+        "datasets" => "GDS",
+        "series" => "GSE",
+        "platforms" => "GPL",
+        "samples" => "GSM",
+        _ => return Err(anyhow!("Unknown GEO FTP directory: {}", gtype)),
+    };
+    if !accession.to_ascii_uppercase().starts_with(prefix) {
+        return Err(anyhow!("{} is not an accession of '{}'", accession, gtype));
+    }
+    let format = match rest {
+        ["soft", fname] if fname.ends_with("_full.soft.gz") => "soft_full",
+        ["soft", ..] => "soft",
+        ["miniml", ..] => "miniml",
+        ["matrix", ..] => "matrix",
+        ["annot", ..] => "annot",
+        ["suppl", ..] => "suppl",
+        [] => return Err(anyhow!("Missing the format directory of {}", accession)),
+        [dir, ..] => return Err(anyhow!("Unknown GEO FTP directory: {}", dir)),
+    };
+    Ok(GEOUrlParts {
+        accession: accession.to_string(),
+        format: format.to_string(),
+        amount: None,
+        scope: None,
+        over_https: Some(over_https),
+    })
+}